@@ -0,0 +1,98 @@
+//! Measures the time taken to retrieve a block with a new `RpcClient` for every request, as
+//! blocks were retrieved before clients were shared, and with the long lived client each
+//! endpoint shares between the rpc workers. Runs against a mock endpoint on localhost over plain
+//! HTTP, so the TLS handshake a new client repeats against a real endpoint is not included.
+//!
+//! `cargo bench --bench rpc_client`
+use axum::{routing::post, Json, Router};
+use serde_json::{json, Value};
+use solana_client::{
+	nonblocking::rpc_client::RpcClient, rpc_client::RpcClientConfig, rpc_config::RpcBlockConfig,
+};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_transaction_status::TransactionDetails;
+use solder::client::{rate_limiter::RateLimiter, rpc_sender::RateLimitedSender};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const REQUESTS: u64 = 500;
+
+async fn get_block(Json(request): Json<Value>) -> Json<Value> {
+	Json(json!({
+		"jsonrpc": "2.0",
+		"id": request["id"],
+		"result": {
+			"blockhash": "11111111111111111111111111111111",
+			"previousBlockhash": "11111111111111111111111111111111",
+			"parentSlot": 0,
+			"transactions": [],
+			"rewards": [],
+			"blockTime": 0,
+			"blockHeight": 0,
+		},
+	}))
+}
+
+fn block_config() -> RpcBlockConfig {
+	RpcBlockConfig {
+		encoding: None,
+		commitment: Some(CommitmentConfig::confirmed()),
+		max_supported_transaction_version: Some(0),
+		rewards: Some(true),
+		transaction_details: Some(TransactionDetails::Full),
+	}
+}
+
+fn report(name: &str, mut latencies: Vec<Duration>) {
+	latencies.sort();
+	let total: Duration = latencies.iter().sum();
+	let percentile = |quantile: f64| latencies[((latencies.len() - 1) as f64 * quantile) as usize];
+
+	println!(
+		"{}: {} blocks, mean {:.2?} p50 {:.2?} p99 {:.2?}",
+		name,
+		latencies.len(),
+		total / latencies.len() as u32,
+		percentile(0.5),
+		percentile(0.99)
+	);
+}
+
+#[tokio::main]
+async fn main() {
+	let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+	let url = format!("http://{}/", listener.local_addr().unwrap());
+	tokio::spawn(async move {
+		axum::serve(listener, Router::new().route("/", post(get_block)))
+			.await
+			.unwrap()
+	});
+
+	let mut latencies = Vec::new();
+	for slot in 0..REQUESTS {
+		let started = Instant::now();
+		let client = RpcClient::new_with_commitment(url.clone(), CommitmentConfig::confirmed());
+		client
+			.get_block_with_config(slot, block_config())
+			.await
+			.unwrap();
+		latencies.push(started.elapsed());
+	}
+	report("New client per block", latencies);
+
+	let sender = RateLimitedSender::new(url.clone(), Arc::new(RateLimiter::new(0.0, 1)), 0);
+	let client = RpcClient::new_sender(
+		sender,
+		RpcClientConfig::with_commitment(CommitmentConfig::confirmed()),
+	);
+	let mut latencies = Vec::new();
+	for slot in 0..REQUESTS {
+		let started = Instant::now();
+		client
+			.get_block_with_config(slot, block_config())
+			.await
+			.unwrap();
+		latencies.push(started.elapsed());
+	}
+	report("Shared client", latencies);
+}
@@ -0,0 +1,101 @@
+//! Measures storage throughput in blocks/sec for each `WriteMode`. Requires a database
+//! configured in `Config.toml` (copied from `Config-template.toml`), synthetic rows are removed
+//! afterwards.
+//!
+//! `cargo bench --bench store_block`
+use rand::Rng;
+use solder::{
+	config::{load_config, StorageConfig, WriteMode},
+	database::create_database_pool,
+	models::{AccountKey, ProcessedBlock, ProcessedTransaction},
+	storage::Storable,
+};
+use std::time::Instant;
+
+const BLOCKS: usize = 50;
+const TRANSACTIONS_PER_BLOCK: usize = 1_000;
+const ACCOUNTS_PER_TRANSACTION: usize = 8;
+/// Slots far below anything on chain so that benchmark rows are easy to remove
+const BASE_SLOT: i64 = -1_000_000;
+
+fn random_base58<const N: usize>() -> String {
+	let mut bytes = [0u8; N];
+	rand::thread_rng().fill(&mut bytes[..]);
+	bs58::encode(bytes).into_string()
+}
+
+fn synthetic_block(slot: i64) -> ProcessedBlock {
+	ProcessedBlock {
+		slot,
+		blockhash: Some(random_base58::<32>()),
+		block_time: Some(0),
+		parent_slot: Some(slot - 1),
+		block_height: None,
+		previous_blockhash: Some(random_base58::<32>()),
+		rewards: Vec::new(),
+		transactions: (0..TRANSACTIONS_PER_BLOCK)
+			.map(|_| ProcessedTransaction {
+				signature: random_base58::<64>(),
+				accounts: (0..ACCOUNTS_PER_TRANSACTION)
+					.map(|i| AccountKey {
+						pubkey: random_base58::<32>(),
+						signer: i == 0,
+						writable: i < 2,
+						from_lookup_table: false,
+					})
+					.collect(),
+				err: None,
+				fee: Some(5000),
+				compute_units_consumed: None,
+				log_message_count: None,
+				token_balance_changes: Vec::new(),
+				lamport_balances: Vec::new(),
+				instructions: Vec::new(),
+			})
+			.collect(),
+	}
+}
+
+#[tokio::main]
+async fn main() {
+	let config = load_config("Config.toml").expect("Config.toml is required for benchmarks");
+	let db_pool = create_database_pool(&config.database).await.unwrap();
+
+	for (i, write_mode) in [WriteMode::Insert, WriteMode::Copy].into_iter().enumerate() {
+		let storage_config = StorageConfig {
+			worker_threads: 1,
+			write_mode,
+		};
+		let base_slot = BASE_SLOT - (i * BLOCKS) as i64;
+		let blocks: Vec<_> = (0..BLOCKS)
+			.map(|n| synthetic_block(base_slot - n as i64))
+			.collect();
+
+		let start = Instant::now();
+		for block in blocks {
+			block
+				.store(db_pool.clone(), &storage_config)
+				.unwrap()
+				.await
+				.unwrap();
+		}
+		let elapsed = start.elapsed();
+
+		println!(
+			"{:?}: {} blocks ({} rows each) in {:.2?}, {:.2} blocks/sec",
+			write_mode,
+			BLOCKS,
+			TRANSACTIONS_PER_BLOCK * ACCOUNTS_PER_TRANSACTION,
+			elapsed,
+			BLOCKS as f64 / elapsed.as_secs_f64()
+		);
+	}
+
+	let conn = db_pool.get().await.unwrap();
+	conn.execute("DELETE FROM blocks WHERE slot < 0", &[])
+		.await
+		.unwrap();
+	conn.execute("DELETE FROM ingestion_state WHERE slot < 0", &[])
+		.await
+		.unwrap();
+}
@@ -0,0 +1,39 @@
+CREATE TABLE blocks (
+    slot BIGINT PRIMARY KEY,
+    blockhash BYTEA NOT NULL UNIQUE,
+    block_time BIGINT NOT NULL
+);
+
+CREATE INDEX idx_blocks_block_time ON blocks(block_time);
+
+CREATE TABLE transactions (
+    id BIGSERIAL PRIMARY KEY,
+    slot BIGINT NOT NULL REFERENCES blocks(slot) ON DELETE CASCADE,
+    signature BYTEA NOT NULL UNIQUE
+);
+
+CREATE INDEX idx_transactions_slot ON transactions(slot);
+
+CREATE TABLE transaction_accounts (
+    transaction_id BIGINT NOT NULL REFERENCES transactions(id) ON DELETE CASCADE,
+    account_index SMALLINT NOT NULL,
+    account BYTEA NOT NULL,
+    PRIMARY KEY (transaction_id, account_index)
+);
+
+CREATE INDEX idx_transaction_accounts_account ON transaction_accounts(account);
+
+CREATE TABLE slot_gaps (
+    slot BIGINT PRIMARY KEY,
+    status TEXT NOT NULL,
+    attempts INT NOT NULL DEFAULT 0,
+    detected_at BIGINT NOT NULL DEFAULT EXTRACT(EPOCH FROM NOW())::BIGINT
+);
+
+CREATE INDEX idx_slot_gaps_status ON slot_gaps(status);
+
+CREATE TABLE ingestion_state (
+    stage TEXT PRIMARY KEY,
+    slot BIGINT NOT NULL,
+    updated_at BIGINT NOT NULL DEFAULT EXTRACT(EPOCH FROM NOW())::BIGINT
+);
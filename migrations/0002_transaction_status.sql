@@ -0,0 +1,7 @@
+ALTER TABLE transactions
+    ADD COLUMN err TEXT,
+    ADD COLUMN fee BIGINT NOT NULL DEFAULT 0,
+    ADD COLUMN compute_units_consumed BIGINT,
+    ADD COLUMN log_message_count INT;
+
+CREATE INDEX idx_transactions_failed ON transactions(slot) WHERE err IS NOT NULL;
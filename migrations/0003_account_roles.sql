@@ -0,0 +1,5 @@
+ALTER TABLE transaction_accounts
+    ADD COLUMN is_signer BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN is_writable BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE INDEX idx_transaction_accounts_signer ON transaction_accounts(account) WHERE is_signer;
@@ -0,0 +1,2 @@
+ALTER TABLE transaction_accounts
+    ADD COLUMN from_lookup_table BOOLEAN NOT NULL DEFAULT FALSE;
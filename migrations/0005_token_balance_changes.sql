@@ -0,0 +1,16 @@
+CREATE TABLE token_balance_changes (
+    transaction_id BIGINT NOT NULL REFERENCES transactions(id) ON DELETE CASCADE,
+    account_index SMALLINT NOT NULL,
+    account BYTEA NOT NULL,
+    mint BYTEA NOT NULL,
+    owner BYTEA,
+    decimals SMALLINT NOT NULL,
+    pre_amount NUMERIC(20, 0) NOT NULL,
+    post_amount NUMERIC(20, 0) NOT NULL,
+    delta NUMERIC(21, 0) NOT NULL,
+    PRIMARY KEY (transaction_id, account_index)
+);
+
+CREATE INDEX idx_token_balance_changes_account ON token_balance_changes(account);
+CREATE INDEX idx_token_balance_changes_owner ON token_balance_changes(owner);
+CREATE INDEX idx_token_balance_changes_mint ON token_balance_changes(mint);
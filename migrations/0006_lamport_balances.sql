@@ -0,0 +1,4 @@
+ALTER TABLE transaction_accounts
+    ADD COLUMN pre_balance BIGINT,
+    ADD COLUMN post_balance BIGINT,
+    ADD COLUMN lamport_delta BIGINT GENERATED ALWAYS AS (post_balance - pre_balance) STORED;
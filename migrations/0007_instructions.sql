@@ -0,0 +1,11 @@
+CREATE TABLE instructions (
+    transaction_id BIGINT NOT NULL REFERENCES transactions(id) ON DELETE CASCADE,
+    position SMALLINT NOT NULL,
+    instruction_index SMALLINT NOT NULL,
+    inner_index SMALLINT,
+    stack_height INT,
+    program_id BYTEA NOT NULL,
+    PRIMARY KEY (transaction_id, position)
+);
+
+CREATE INDEX idx_instructions_program_id ON instructions(program_id, transaction_id);
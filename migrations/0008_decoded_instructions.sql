@@ -0,0 +1,2 @@
+ALTER TABLE instructions
+    ADD COLUMN decoded JSONB;
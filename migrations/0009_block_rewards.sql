@@ -0,0 +1,17 @@
+ALTER TABLE blocks
+    ADD COLUMN leader BYTEA;
+
+CREATE INDEX idx_blocks_leader ON blocks(leader, slot);
+
+CREATE TABLE block_rewards (
+    slot BIGINT NOT NULL REFERENCES blocks(slot) ON DELETE CASCADE,
+    reward_index SMALLINT NOT NULL,
+    pubkey BYTEA NOT NULL,
+    lamports BIGINT NOT NULL,
+    post_balance BIGINT NOT NULL,
+    reward_type TEXT,
+    commission SMALLINT,
+    PRIMARY KEY (slot, reward_index)
+);
+
+CREATE INDEX idx_block_rewards_pubkey ON block_rewards(pubkey);
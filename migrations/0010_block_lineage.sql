@@ -0,0 +1,6 @@
+ALTER TABLE blocks
+    ADD COLUMN parent_slot BIGINT,
+    ADD COLUMN block_height BIGINT,
+    ADD COLUMN previous_blockhash BYTEA;
+
+CREATE INDEX idx_blocks_parent_slot ON blocks(parent_slot);
@@ -0,0 +1,4 @@
+ALTER TABLE blocks
+    ADD COLUMN commitment TEXT NOT NULL DEFAULT 'confirmed';
+
+CREATE INDEX idx_blocks_confirmed ON blocks(slot) WHERE commitment = 'confirmed';
@@ -0,0 +1,5 @@
+ALTER TABLE transactions
+    DROP CONSTRAINT transactions_signature_key,
+    ADD CONSTRAINT transactions_slot_signature_key UNIQUE (slot, signature);
+
+CREATE INDEX idx_transactions_signature ON transactions(signature);
@@ -0,0 +1,2 @@
+ALTER TABLE blocks
+    ALTER COLUMN blockhash DROP NOT NULL;
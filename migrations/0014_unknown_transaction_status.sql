@@ -0,0 +1,3 @@
+ALTER TABLE transactions
+    ALTER COLUMN fee DROP NOT NULL,
+    ALTER COLUMN fee DROP DEFAULT;
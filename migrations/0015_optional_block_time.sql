@@ -0,0 +1,2 @@
+ALTER TABLE blocks
+    ALTER COLUMN block_time DROP NOT NULL;
@@ -2,7 +2,11 @@ use {
 	crate::{
 		api::query::*,
 		database::DatabasePool,
-		models::{Aggregate, BlockResponse, BlockTransactions, TransactionResponse},
+		models::{
+			to_base58, Aggregate, BalanceHistory, BalancePoint, BlockLink, BlockResponse,
+			BlockTransactions, ProgramInvocation, TokenTransfer, TransactionResponse,
+			ValidatorBlock, ValidatorBlocks,
+		},
 	},
 	axum::{
 		extract::{Path, Query, State},
@@ -11,7 +15,7 @@ use {
 		Json,
 	},
     std::collections::HashMap,
-	serde_json::Value,
+	serde_json::{json, Value},
 	tokio_postgres::row::Row,
 };
 
@@ -34,10 +38,13 @@ pub(crate) async fn handler_404() -> impl IntoResponse {
 ///
 /// Parameters:
 /// `to: Option<String>`,
-/// `from: Option<String>`.
+/// `from: Option<String>`,
+/// `status: Option<String>`, either `success` or `failed`,
+/// `role: Option<String>`, either `signer` or `writable`.
 pub async fn account_handler(
 	State(pool): State<DatabasePool>,
 	Path(pubkey): Path<String>,
+	Query(CommitmentParams { commitment }): Query<CommitmentParams>,
 	Query(params): Query<AccountQueryParams>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<Value>)> {
 	match handle_query(
@@ -46,7 +53,10 @@ pub async fn account_handler(
 			pubkey,
 			from: params.from,
 			to: params.to,
+			status: params.status,
+			role: params.role,
 		},
+		commitment,
 	)
 	.await
 	{
@@ -55,26 +65,149 @@ pub async fn account_handler(
 	}
 }
 
+/// `/api/account/:pubkey/balances?to=YYYY-MM-DD&from=YYYY-MM-DD`, the account's native SOL
+/// balance after each transaction in the range, will return
+/// `(StatusCode::ERROR_TYPE, description)` if not `Ok()`.
+///
+/// Parameters:
+/// `to: Option<String>`,
+/// `from: Option<String>`.
+pub async fn balances_handler(
+	State(pool): State<DatabasePool>,
+	Path(pubkey): Path<String>,
+	Query(CommitmentParams { commitment }): Query<CommitmentParams>,
+	Query(params): Query<BalanceQueryParams>,
+) -> Result<impl IntoResponse, (StatusCode, Json<Value>)> {
+	match handle_query(
+		pool.clone(),
+		QueryType::Balances {
+			pubkey: pubkey.clone(),
+			from: params.from,
+			to: params.to,
+		},
+		commitment,
+	)
+	.await
+	{
+		Ok(rows) => Ok(build_balances_response(pubkey, rows)),
+		Err(e) => Err(e),
+	}
+}
+
+/// `/api/account/:pubkey/token-transfers?mint=...`, token balance changes of the token accounts
+/// owned by `pubkey` or of the token account itself, will return
+/// `(StatusCode::ERROR_TYPE, description)` if not `Ok()`.
+///
+/// Parameters:
+/// `to: Option<String>`,
+/// `from: Option<String>`,
+/// `mint: Option<String>`.
+pub async fn token_transfers_handler(
+	State(pool): State<DatabasePool>,
+	Path(pubkey): Path<String>,
+	Query(CommitmentParams { commitment }): Query<CommitmentParams>,
+	Query(params): Query<TokenTransferQueryParams>,
+) -> Result<impl IntoResponse, (StatusCode, Json<Value>)> {
+	match handle_query(
+		pool.clone(),
+		QueryType::TokenTransfers {
+			pubkey,
+			from: params.from,
+			to: params.to,
+			mint: params.mint,
+		},
+		commitment,
+	)
+	.await
+	{
+		Ok(rows) => Ok(build_token_transfers_response(rows)),
+		Err(e) => Err(e),
+	}
+}
+
+/// `/api/program/:program_id?to=YYYY-MM-DD&from=YYYY-MM-DD`, top level and inner invocations of
+/// a program, will return `(StatusCode::ERROR_TYPE, description)` if not `Ok()`.
+///
+/// Parameters:
+/// `to: Option<String>`,
+/// `from: Option<String>`.
+pub async fn program_handler(
+	State(pool): State<DatabasePool>,
+	Path(program_id): Path<String>,
+	Query(CommitmentParams { commitment }): Query<CommitmentParams>,
+	Query(params): Query<ProgramQueryParams>,
+) -> Result<impl IntoResponse, (StatusCode, Json<Value>)> {
+	match handle_query(
+		pool.clone(),
+		QueryType::Program {
+			program_id,
+			from: params.from,
+			to: params.to,
+		},
+		commitment,
+	)
+	.await
+	{
+		Ok(rows) => Ok(build_program_response(rows)),
+		Err(e) => Err(e),
+	}
+}
+
+/// `/api/validator/:identity/blocks?to=YYYY-MM-DD&from=YYYY-MM-DD`, blocks produced by a validator
+/// identity and the rewards it earned from them, will return
+/// `(StatusCode::ERROR_TYPE, description)` if not `Ok()`.
+///
+/// Parameters:
+/// `to: Option<String>`,
+/// `from: Option<String>`.
+pub async fn validator_blocks_handler(
+	State(pool): State<DatabasePool>,
+	Path(identity): Path<String>,
+	Query(CommitmentParams { commitment }): Query<CommitmentParams>,
+	Query(params): Query<ValidatorQueryParams>,
+) -> Result<impl IntoResponse, (StatusCode, Json<Value>)> {
+	match handle_query(
+		pool.clone(),
+		QueryType::Validator {
+			identity: identity.clone(),
+			from: params.from,
+			to: params.to,
+		},
+		commitment,
+	)
+	.await
+	{
+		Ok(rows) => Ok(build_validator_blocks_response(identity, rows)),
+		Err(e) => Err(e),
+	}
+}
+
 /// `/api/transaction/:signature`, takes a connection pool to the database as a state parameter
 /// for data retrieval will return `(StatusCode::ERROR_TYPE, description)` if not `Ok()`.
 pub async fn transaction_handler(
 	State(pool): State<DatabasePool>,
 	Path(signature): Path<String>,
+	Query(CommitmentParams { commitment }): Query<CommitmentParams>,
 ) -> impl IntoResponse {
-	match handle_query(pool.clone(), QueryType::Transaction(signature)).await {
-		Ok(rows) => Ok(build_transaction_response(rows)),
+	match handle_query(pool.clone(), QueryType::Transaction(signature), commitment).await {
+		Ok(rows) => build_transaction_response(rows),
 		Err(e) => Err(e),
 	}
 }
 
 /// `/api/block/:blockhash`, takes connection pool to the database as a state parameter
 /// for data retrieval will return `(StatusCode::ERROR_TYPE, description)` if not `Ok()`.
+/// The response links to the parent block and any stored child blocks.
 pub async fn block_handler(
 	State(pool): State<DatabasePool>,
 	Path(blockhash): Path<String>,
-) -> impl IntoResponse {
-	match handle_query(pool.clone(), QueryType::Block(blockhash)).await {
-		Ok(rows) => Ok(build_block_response(rows)),
+	Query(CommitmentParams { commitment }): Query<CommitmentParams>,
+) -> Result<impl IntoResponse, (StatusCode, Json<Value>)> {
+	match handle_query(pool.clone(), QueryType::Block(blockhash), commitment).await {
+		Ok(rows) => {
+			let links = get_block_links(pool, &rows, commitment).await?;
+			build_block_response(rows, links)
+		}
 		Err(e) => Err(e),
 	}
 }
@@ -84,19 +217,36 @@ pub async fn block_handler(
 pub async fn slot_handler(
 	State(pool): State<DatabasePool>,
 	Path(slot_number): Path<i64>,
-) -> impl IntoResponse {
-	match handle_query(pool.clone(), QueryType::Slot(slot_number)).await {
-		Ok(rows) => Ok(build_slot_response(rows)),
+	Query(CommitmentParams { commitment }): Query<CommitmentParams>,
+) -> Result<impl IntoResponse, (StatusCode, Json<Value>)> {
+	match handle_query(pool.clone(), QueryType::Slot(slot_number), commitment).await {
+		Ok(rows) => {
+			let links = get_block_links(pool, &rows, commitment).await?;
+			build_slot_response(rows, links)
+		}
 		Err(e) => Err(e),
 	}
 }
 
+/// Parent and child blocks of the block in `rows`
+async fn get_block_links(
+	pool: DatabasePool,
+	rows: &[Row],
+	commitment: Option<CommitmentFilter>,
+) -> Result<Vec<Row>, (StatusCode, Json<Value>)> {
+	match rows.first() {
+		Some(row) => handle_query(pool, QueryType::BlockLinks(row.get("slot")), commitment).await,
+		None => Ok(Vec::new()),
+	}
+}
+
 /// Query handler
 async fn handle_query(
 	pool: DatabasePool,
 	query_type: QueryType,
+	commitment: Option<CommitmentFilter>,
 ) -> Result<Vec<Row>, (StatusCode, Json<Value>)> {
-	execute_query(pool, query_type).await
+	execute_query(pool, query_type, commitment).await
 }
 
 /// Builds an `axum::response::Response` from a `Vec<Row>` should never throw an error.
@@ -104,73 +254,257 @@ fn build_account_response(rows: Vec<Row>) -> impl IntoResponse {
 	let aggregate: Vec<Aggregate> = rows
 		.into_iter()
 		.map(|row| Aggregate {
-			blockhash: row.get("blockhash"),
+			blockhash: row.get::<_, Option<&[u8]>>("blockhash").map(to_base58),
 			slot: row.get("slot"),
 			block_time: row.get("block_time"),
-			signature: row.get("signature"),
-			account: row.get("account"),
+			signature: get_base58(&row, "signature"),
+			account: get_base58(&row, "account"),
+			is_signer: row.get("is_signer"),
+			is_writable: row.get("is_writable"),
+			from_lookup_table: row.get("from_lookup_table"),
+			pre_balance: row.get("pre_balance"),
+			post_balance: row.get("post_balance"),
+			lamport_delta: row.get("lamport_delta"),
 		})
 		.collect();
 	(StatusCode::OK, Json(aggregate))
 }
 
-/// builds an `axum::response::response` from a `vec<row>` should never throw an error.
-fn build_transaction_response(rows: Vec<Row>) -> impl IntoResponse {
-	let first_row = &rows[0];
+/// Builds an `axum::response::Response` from a `Vec<Row>` should never throw an error. Rows are
+/// ordered by slot so the first and last rows bound the range.
+fn build_balances_response(account: String, rows: Vec<Row>) -> impl IntoResponse {
+	let history: Vec<BalancePoint> = rows
+		.into_iter()
+		.map(|row| BalancePoint {
+			slot: row.get("slot"),
+			block_time: row.get("block_time"),
+			signature: get_base58(&row, "signature"),
+			pre_balance: row.get("pre_balance"),
+			post_balance: row.get("post_balance"),
+			lamport_delta: row.get("lamport_delta"),
+		})
+		.collect();
+	let start_balance = history.first().map(|point| point.pre_balance);
+	let end_balance = history.last().map(|point| point.post_balance);
+
+	(
+		StatusCode::OK,
+		Json(BalanceHistory {
+			account,
+			start_balance,
+			end_balance,
+			net_change: history.iter().map(|point| point.lamport_delta).sum(),
+			history,
+		}),
+	)
+}
+
+/// Builds an `axum::response::Response` from a `Vec<Row>` should never throw an error.
+fn build_token_transfers_response(rows: Vec<Row>) -> impl IntoResponse {
+	let transfers: Vec<TokenTransfer> = rows
+		.into_iter()
+		.map(|row| TokenTransfer {
+			blockhash: row.get::<_, Option<&[u8]>>("blockhash").map(to_base58),
+			slot: row.get("slot"),
+			block_time: row.get("block_time"),
+			signature: get_base58(&row, "signature"),
+			success: get_success(&row),
+			account: get_base58(&row, "account"),
+			mint: get_base58(&row, "mint"),
+			owner: row.get::<_, Option<&[u8]>>("owner").map(to_base58),
+			decimals: row.get("decimals"),
+			pre_amount: row.get("pre_amount"),
+			post_amount: row.get("post_amount"),
+			delta: row.get("delta"),
+		})
+		.collect();
+	(StatusCode::OK, Json(transfers))
+}
+
+/// Builds an `axum::response::Response` from a `Vec<Row>` should never throw an error.
+fn build_program_response(rows: Vec<Row>) -> impl IntoResponse {
+	let invocations: Vec<ProgramInvocation> = rows
+		.into_iter()
+		.map(|row| ProgramInvocation {
+			blockhash: row.get::<_, Option<&[u8]>>("blockhash").map(to_base58),
+			slot: row.get("slot"),
+			block_time: row.get("block_time"),
+			signature: get_base58(&row, "signature"),
+			success: get_success(&row),
+			program_id: get_base58(&row, "program_id"),
+			instruction_index: row.get("instruction_index"),
+			inner_index: row.get("inner_index"),
+			stack_height: row.get("stack_height"),
+			decoded: row
+				.get::<_, Option<&str>>("decoded")
+				.and_then(|decoded| serde_json::from_str(decoded).ok()),
+		})
+		.collect();
+	(StatusCode::OK, Json(invocations))
+}
+
+/// Builds an `axum::response::Response` from a `Vec<Row>` should never throw an error.
+fn build_validator_blocks_response(identity: String, rows: Vec<Row>) -> impl IntoResponse {
+	let blocks: Vec<ValidatorBlock> = rows
+		.into_iter()
+		.map(|row| ValidatorBlock {
+			blockhash: row.get::<_, Option<&[u8]>>("blockhash").map(to_base58),
+			slot: row.get("slot"),
+			block_time: row.get("block_time"),
+			reward_lamports: row.get("reward_lamports"),
+		})
+		.collect();
+
 	(
+		StatusCode::OK,
+		Json(ValidatorBlocks {
+			identity,
+			block_count: blocks.len(),
+			total_rewards: blocks.iter().map(|block| block.reward_lamports).sum(),
+			blocks,
+		}),
+	)
+}
+
+/// Builds an `axum::response::Response` from a `Vec<Row>`, returns `NOT_FOUND` if the
+/// transaction is not stored.
+fn build_transaction_response(
+	rows: Vec<Row>,
+) -> Result<impl IntoResponse, (StatusCode, Json<Value>)> {
+	let first_row = rows.first().ok_or_else(|| not_found("Transaction"))?;
+	let err: Option<String> = first_row.get("err");
+	Ok((
 		StatusCode::OK,
 		Json(TransactionResponse {
-			blockhash: first_row.get("blockhash"),
+			blockhash: first_row
+				.get::<_, Option<&[u8]>>("blockhash")
+				.map(to_base58),
 			slot: first_row.get("slot"),
 			block_time: first_row.get("block_time"),
-			signature: first_row.get("signature"),
+			commitment: first_row.get("commitment"),
+			signature: get_base58(first_row, "signature"),
+			success: get_success(first_row),
+			err,
+			fee: first_row.get("fee"),
+			compute_units_consumed: first_row.get("compute_units_consumed"),
+			log_message_count: first_row.get("log_message_count"),
 			accounts: rows
-				.into_iter()
-				.map(|row| row.get("account"))
+				.iter()
+				.map(|row| get_base58(row, "account"))
 				.collect::<Vec<String>>(),
 		}),
-	)
+	))
 }
-/// Builds an `axum::response::Response` from a `Vec<Row>` should never throw an error.
-/// Uses a Hashmap to avoid cloning the vector, should keep lookups to O(1).
+/// Builds an `axum::response::Response` from a `Vec<Row>`, returns `NOT_FOUND` if the block is
+/// not stored. Uses a Hashmap to avoid cloning the vector, should keep lookups to O(1).
 /// Had previously used Itertools::chunk_by but required cloning the Vec.
-fn build_block_response(rows: Vec<Row>) -> impl IntoResponse {
-	let mut transaction_map: HashMap<String, Vec<String>> = HashMap::new();
+fn build_block_response(
+	rows: Vec<Row>,
+	links: Vec<Row>,
+) -> Result<impl IntoResponse, (StatusCode, Json<Value>)> {
+	let mut transaction_map: HashMap<String, BlockTransactions> = HashMap::new();
 	let mut first_row = None;
 
 	for row in rows {
 		if first_row.is_none() {
 			first_row = Some(row.clone());
 		}
-		let signature: String = row.get("signature");
-		let account: String = row.get("account");
-		transaction_map
-			.entry(signature)
-			.or_insert_with(Vec::new)
-			.push(account);
+		// A block without transactions is a single row without a signature
+		let Some(signature) = row.get::<_, Option<&[u8]>>("signature").map(to_base58) else {
+			continue;
+		};
+		let accounts = &mut transaction_map
+			.entry(signature.clone())
+			.or_insert_with(|| {
+				let err: Option<String> = row.get("err");
+				BlockTransactions {
+					signature,
+					success: get_success(&row),
+					err,
+					fee: row.get("fee"),
+					compute_units_consumed: row.get("compute_units_consumed"),
+					log_message_count: row.get("log_message_count"),
+					accounts: Vec::new(),
+				}
+			})
+			.accounts;
+		if let Some(account) = row.get::<_, Option<&[u8]>>("account").map(to_base58) {
+			accounts.push(account);
+		}
 	}
 
-	let transactions: Vec<BlockTransactions> = transaction_map
-		.into_iter()
-		.map(|(signature, accounts)| BlockTransactions {
-			signature,
-			accounts,
-		})
-		.collect();
+	let transactions: Vec<BlockTransactions> = transaction_map.into_values().collect();
 
-	let first_row = first_row.unwrap();
-	(
+	let first_row = first_row.ok_or_else(|| not_found("Block"))?;
+	let slot: i64 = first_row.get("slot");
+	let (parents, children): (Vec<BlockLink>, Vec<BlockLink>) = links
+		.iter()
+		.map(|row| BlockLink::new(row.get("slot"), get_base58(row, "blockhash")))
+		.partition(|link| link.slot < slot);
+	Ok((
 		StatusCode::OK,
 		Json(BlockResponse {
-			blockhash: first_row.get("blockhash"),
-			slot: first_row.get("slot"),
+			blockhash: first_row
+				.get::<_, Option<&[u8]>>("blockhash")
+				.map(to_base58),
+			slot,
 			block_time: first_row.get("block_time"),
+			commitment: first_row.get("commitment"),
+			leader: first_row.get::<_, Option<&[u8]>>("leader").map(to_base58),
+			parent_slot: first_row.get("parent_slot"),
+			block_height: first_row.get("block_height"),
+			previous_blockhash: first_row
+				.get::<_, Option<&[u8]>>("previous_blockhash")
+				.map(to_base58),
+			parent: parents.into_iter().next(),
+			children,
 			transactions,
 		}),
-	)
+	))
 }
 
 /// Alias for `build_block_response`
-fn build_slot_response(rows: Vec<Row>) -> impl IntoResponse {
-	build_block_response(rows)
+fn build_slot_response(
+	rows: Vec<Row>,
+	links: Vec<Row>,
+) -> Result<impl IntoResponse, (StatusCode, Json<Value>)> {
+	build_block_response(rows, links)
+}
+
+/// Error response for a transaction or block that is not stored
+fn not_found(resource: &str) -> (StatusCode, Json<Value>) {
+	(
+		StatusCode::NOT_FOUND,
+		Json(json!({"error": format!("{} not found", resource)})),
+	)
+}
+
+/// `None` if the transaction was stored without meta, its status is then unknown
+fn get_success(row: &Row) -> Option<bool> {
+	row.get::<_, Option<i64>>("fee")
+		.map(|_| row.get::<_, Option<String>>("err").is_none())
+}
+
+/// Pubkeys, signatures and blockhashes are stored as bytes and returned as base58 strings
+fn get_base58(row: &Row, column: &str) -> String {
+	to_base58(row.get::<_, &[u8]>(column))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_empty_rows_are_not_found() {
+		let status = |response: axum::response::Response| response.status();
+
+		assert_eq!(
+			status(build_transaction_response(Vec::new()).into_response()),
+			StatusCode::NOT_FOUND
+		);
+		assert_eq!(
+			status(build_block_response(Vec::new(), Vec::new()).into_response()),
+			StatusCode::NOT_FOUND
+		);
+	}
 }
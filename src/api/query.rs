@@ -1,6 +1,10 @@
-use crate::database::DatabasePool;
+use crate::{
+	database::DatabasePool,
+	error::Result as AppResult,
+	models::{hash_bytes, pubkey_bytes, signature_bytes},
+};
 use axum::{http::StatusCode, Json};
-use chrono::{NaiveDateTime, TimeZone, Utc};
+use chrono::{NaiveDate, TimeZone, Utc};
 use serde::Deserialize;
 use serde_json::{json, Value};
 use tokio_postgres::row::Row;
@@ -8,19 +12,74 @@ use tokio_postgres::row::Row;
 /// Type alias for code cleanliness
 type QueryParams = Box<dyn tokio_postgres::types::ToSql + Sync + Send>;
 
+/// Limits results to blocks that reached a commitment level, every stored block is at least
+/// confirmed so only `finalized` narrows results
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CommitmentFilter {
+	Confirmed,
+	Finalized,
+}
+
+/// `?commitment=confirmed|finalized`, accepted by every endpoint
+#[derive(Deserialize)]
+pub struct CommitmentParams {
+	pub commitment: Option<CommitmentFilter>,
+}
+
 /// Enum to define the types of query the server can receive and their respectie parameters
 #[derive(Deserialize)]
 pub enum QueryType {
 	Transaction(String),
 	Block(String),
 	Slot(i64),
+	BlockLinks(i64),
 	Account {
 		pubkey: String,
 		from: Option<String>,
 		to: Option<String>,
+		status: Option<StatusFilter>,
+		role: Option<RoleFilter>,
+	},
+	TokenTransfers {
+		pubkey: String,
+		from: Option<String>,
+		to: Option<String>,
+		mint: Option<String>,
+	},
+	Balances {
+		pubkey: String,
+		from: Option<String>,
+		to: Option<String>,
+	},
+	Program {
+		program_id: String,
+		from: Option<String>,
+		to: Option<String>,
+	},
+	Validator {
+		identity: String,
+		from: Option<String>,
+		to: Option<String>,
 	},
 }
 
+/// Filters an account's transactions by the role the account had in them
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RoleFilter {
+	Signer,
+	Writable,
+}
+
+/// Filters transactions by whether they succeeded or failed
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StatusFilter {
+	Success,
+	Failed,
+}
+
 /// Optional parameters for `/api/account/{pubkey}`, `from` and `to` should be provided
 /// in YYYY-MM-DD format.
 ///
@@ -28,16 +87,55 @@ pub enum QueryType {
 /// till `to`.
 /// If `to` is not provided, query will retrieve all data from `from` till now.
 /// If neither parameter is provided, all data regarding an account is retrieved.
+///
+/// `status` optionally limits results to `success` or `failed` transactions, `role` to
+/// transactions the account signed (`signer`) or was writable in (`writable`).
 #[derive(Deserialize)]
 pub struct AccountQueryParams {
 	pub from: Option<String>, // YYYY-MM-DD
 	pub to: Option<String>,   // YYYY-MM-DD
+	pub status: Option<StatusFilter>,
+	pub role: Option<RoleFilter>,
+}
+
+/// Optional parameters for `/api/account/{pubkey}/token-transfers`, `from` and `to` work as in
+/// `AccountQueryParams`, `mint` limits results to a single token.
+#[derive(Deserialize)]
+pub struct TokenTransferQueryParams {
+	pub from: Option<String>, // YYYY-MM-DD
+	pub to: Option<String>,   // YYYY-MM-DD
+	pub mint: Option<String>,
+}
+
+/// Optional parameters for `/api/account/{pubkey}/balances`, `from` and `to` work as in
+/// `AccountQueryParams`.
+#[derive(Deserialize)]
+pub struct BalanceQueryParams {
+	pub from: Option<String>, // YYYY-MM-DD
+	pub to: Option<String>,   // YYYY-MM-DD
+}
+
+/// Optional parameters for `/api/program/{program_id}`, `from` and `to` work as in
+/// `AccountQueryParams`.
+#[derive(Deserialize)]
+pub struct ProgramQueryParams {
+	pub from: Option<String>, // YYYY-MM-DD
+	pub to: Option<String>,   // YYYY-MM-DD
+}
+
+/// Optional parameters for `/api/validator/{identity}/blocks`, `from` and `to` work as in
+/// `AccountQueryParams`.
+#[derive(Deserialize)]
+pub struct ValidatorQueryParams {
+	pub from: Option<String>, // YYYY-MM-DD
+	pub to: Option<String>,   // YYYY-MM-DD
 }
 
 /// Builds and executes a database query
 pub async fn execute_query(
 	pool: DatabasePool,
 	query_type: QueryType,
+	commitment: Option<CommitmentFilter>,
 ) -> Result<Vec<Row>, (StatusCode, Json<Value>)> {
 	let client = pool.get().await.map_err(|e| {
 		log::error!("{}", e);
@@ -47,7 +145,12 @@ pub async fn execute_query(
 		)
 	})?;
 
-	let (base_query, query_params) = build_query(query_type);
+	let (base_query, query_params) = build_query(query_type, commitment).map_err(|e| {
+		(
+			StatusCode::BAD_REQUEST,
+			Json(json!({"error": format!("Invalid request: {}", e)})),
+		)
+	})?;
 
 	let query_refs: Vec<&(dyn tokio_postgres::types::ToSql + Sync)> = query_params
 		.iter()
@@ -67,35 +170,220 @@ pub async fn execute_query(
 }
 
 /// Build database query from query type, for types `QueryType::Transaction`, `QueryType::Slot`,
-/// and `QueryType::Block` the query is built solely from the Path. For `QueryType::Account` 
-/// additional time parameters may be passed in. TODO: Pagination
-fn build_query(query_type: QueryType) -> (String, Vec<QueryParams>) {
-	let mut base_query = "SELECT * FROM transaction_accounts WHERE".to_string();
-	let mut query_params: Vec<QueryParams> = Vec::new();
-
+/// and `QueryType::Block` the query is built solely from the Path. For `QueryType::Account`
+/// additional time parameters may be passed in. Rows are built by joining `blocks`,
+/// `transactions` and `transaction_accounts`, blocks are returned even without transactions.
+/// Base58 path parameters are decoded to the stored
+/// bytes. `QueryType::TokenTransfers` reads `token_balance_changes` instead of
+/// `transaction_accounts` and `QueryType::Program` reads `instructions`, `QueryType::Balances`
+/// only returns an account's rows that carry balances. Every query can be limited to finalized
+/// blocks with `commitment`. TODO: Pagination
+fn build_query(
+	query_type: QueryType,
+	commitment: Option<CommitmentFilter>,
+) -> AppResult<(String, Vec<QueryParams>)> {
 	match query_type {
-		QueryType::Transaction(signature) => {
-			base_query.push_str(" signature = $1");
-			query_params.push(Box::new(signature) as QueryParams);
-		}
+		// A transaction included in blocks of competing forks is returned from the finalized
+		// block, or the latest one until a block is finalized
+		QueryType::Transaction(signature) => build_accounts_query(
+			" t.signature = $1 AND t.slot = (
+			SELECT i.slot FROM transactions i JOIN blocks ib ON ib.slot = i.slot
+			WHERE i.signature = $1
+			ORDER BY ib.commitment = 'finalized' DESC, i.slot DESC
+			LIMIT 1)"
+				.to_string(),
+			vec![Box::new(signature_bytes(&signature)?)],
+			commitment,
+		),
 		QueryType::Slot(slot) => {
-			base_query.push_str(" slot = $1");
-			query_params.push(Box::new(slot) as QueryParams);
+			build_block_query(" b.slot = $1".to_string(), vec![Box::new(slot)], commitment)
+		}
+		QueryType::Block(blockhash) => build_block_query(
+			" b.blockhash = $1".to_string(),
+			vec![Box::new(hash_bytes(&blockhash)?)],
+			commitment,
+		),
+		QueryType::Account {
+			pubkey,
+			from,
+			to,
+			status,
+			role,
+		} => {
+			let mut condition = " a.account = $1".to_string();
+			let mut query_params: Vec<QueryParams> = vec![Box::new(pubkey_bytes(&pubkey)?)];
+			add_date_conditions(&mut condition, &mut query_params, &from, &to);
+			add_status_condition(&mut condition, status);
+			add_role_condition(&mut condition, role);
+			build_accounts_query(condition, query_params, commitment)
 		}
-		QueryType::Block(blockhash) => {
-			base_query.push_str(" blockhash = $1");
-			query_params.push(Box::new(blockhash) as QueryParams);
+		QueryType::Balances { pubkey, from, to } => {
+			let mut condition = " a.account = $1 AND a.pre_balance IS NOT NULL".to_string();
+			let mut query_params: Vec<QueryParams> = vec![Box::new(pubkey_bytes(&pubkey)?)];
+			add_date_conditions(&mut condition, &mut query_params, &from, &to);
+			build_accounts_query(condition, query_params, commitment)
 		}
-		QueryType::Account { pubkey, from, to } => {
-			base_query.push_str(" account = $1");
-			query_params.push(Box::new(pubkey) as QueryParams);
-			add_date_conditions(&mut base_query, &mut query_params, &from, &to);
+		QueryType::TokenTransfers {
+			pubkey,
+			from,
+			to,
+			mint,
+		} => build_token_transfers_query(pubkey, from, to, mint, commitment),
+		QueryType::Program {
+			program_id,
+			from,
+			to,
+		} => build_program_query(program_id, from, to, commitment),
+		QueryType::Validator { identity, from, to } => {
+			build_validator_query(identity, from, to, commitment)
 		}
+		QueryType::BlockLinks(slot) => build_block_links_query(slot, commitment),
+	}
+}
+
+/// Rows joining `blocks`, `transactions` and `transaction_accounts` that match `condition`,
+/// ordered by block, transaction and account
+fn build_accounts_query(
+	condition: String,
+	query_params: Vec<QueryParams>,
+	commitment: Option<CommitmentFilter>,
+) -> AppResult<(String, Vec<QueryParams>)> {
+	let mut base_query = format!(
+		"SELECT b.blockhash, b.slot, b.block_time, b.leader, b.commitment,
+		b.parent_slot, b.block_height, b.previous_blockhash, t.signature, t.err, t.fee, t.compute_units_consumed,
+		t.log_message_count, a.account, a.is_signer, a.is_writable, a.from_lookup_table,
+		a.pre_balance, a.post_balance, a.lamport_delta
+		FROM transactions t
+		JOIN blocks b ON b.slot = t.slot
+		JOIN transaction_accounts a ON a.transaction_id = t.id
+		WHERE{}",
+		condition
+	);
+
+	add_commitment_condition(&mut base_query, commitment);
+	base_query.push_str(" ORDER BY b.slot ASC, t.id ASC, a.account_index ASC");
+
+	Ok((base_query, query_params))
+}
+
+/// Rows of the block matching `condition` left joined with its transactions and their accounts,
+/// a block without transactions is a single row whose transaction and account columns are `NULL`
+fn build_block_query(
+	condition: String,
+	query_params: Vec<QueryParams>,
+	commitment: Option<CommitmentFilter>,
+) -> AppResult<(String, Vec<QueryParams>)> {
+	let mut base_query = format!(
+		"SELECT b.blockhash, b.slot, b.block_time, b.leader, b.commitment,
+		b.parent_slot, b.block_height, b.previous_blockhash, t.signature, t.err, t.fee, t.compute_units_consumed,
+		t.log_message_count, a.account
+		FROM blocks b
+		LEFT JOIN transactions t ON t.slot = b.slot
+		LEFT JOIN transaction_accounts a ON a.transaction_id = t.id
+		WHERE{}",
+		condition
+	);
+
+	add_commitment_condition(&mut base_query, commitment);
+	base_query.push_str(" ORDER BY b.slot ASC, t.id ASC, a.account_index ASC");
+
+	Ok((base_query, query_params))
+}
+
+/// Token balance changes of accounts owned by `pubkey` or of the token account `pubkey` itself.
+/// Amounts are returned as text as they may not fit in an `i64`.
+fn build_token_transfers_query(
+	pubkey: String,
+	from: Option<String>,
+	to: Option<String>,
+	mint: Option<String>,
+	commitment: Option<CommitmentFilter>,
+) -> AppResult<(String, Vec<QueryParams>)> {
+	let mut base_query = "SELECT b.blockhash, b.slot, b.block_time, t.signature, t.err, t.fee,
+		c.account, c.mint, c.owner, c.decimals, c.pre_amount::TEXT AS pre_amount,
+		c.post_amount::TEXT AS post_amount, c.delta::TEXT AS delta
+		FROM token_balance_changes c
+		JOIN transactions t ON t.id = c.transaction_id
+		JOIN blocks b ON b.slot = t.slot
+		WHERE (c.owner = $1 OR c.account = $1)"
+		.to_string();
+	let mut query_params: Vec<QueryParams> = vec![Box::new(pubkey_bytes(&pubkey)?)];
+
+	if let Some(mint) = mint {
+		query_params.push(Box::new(pubkey_bytes(&mint)?));
+		base_query.push_str(&format!(" AND c.mint = ${}", query_params.len()));
 	}
+	add_date_conditions(&mut base_query, &mut query_params, &from, &to);
+	add_commitment_condition(&mut base_query, commitment);
+
+	base_query.push_str(" ORDER BY b.slot ASC, t.id ASC, c.account_index ASC");
+
+	Ok((base_query, query_params))
+}
+
+/// Invocations of a program, top level and inner, ordered by execution
+fn build_program_query(
+	program_id: String,
+	from: Option<String>,
+	to: Option<String>,
+	commitment: Option<CommitmentFilter>,
+) -> AppResult<(String, Vec<QueryParams>)> {
+	let mut base_query = "SELECT b.blockhash, b.slot, b.block_time, t.signature, t.err, t.fee,
+		i.program_id, i.instruction_index, i.inner_index, i.stack_height, i.decoded::TEXT AS decoded
+		FROM instructions i
+		JOIN transactions t ON t.id = i.transaction_id
+		JOIN blocks b ON b.slot = t.slot
+		WHERE i.program_id = $1"
+		.to_string();
+	let mut query_params: Vec<QueryParams> = vec![Box::new(pubkey_bytes(&program_id)?)];
+
+	add_date_conditions(&mut base_query, &mut query_params, &from, &to);
+	add_commitment_condition(&mut base_query, commitment);
+
+	base_query.push_str(" ORDER BY b.slot ASC, t.id ASC, i.position ASC");
+
+	Ok((base_query, query_params))
+}
+
+/// The stored parent of a block and the stored blocks that name it as their parent, blocks stored
+/// without a header cannot be linked to by blockhash
+fn build_block_links_query(
+	slot: i64,
+	commitment: Option<CommitmentFilter>,
+) -> AppResult<(String, Vec<QueryParams>)> {
+	let mut base_query = "SELECT b.slot, b.blockhash
+		FROM blocks b
+		WHERE (b.slot = (SELECT parent_slot FROM blocks WHERE slot = $1) OR b.parent_slot = $1)
+		AND b.blockhash IS NOT NULL"
+		.to_string();
+
+	add_commitment_condition(&mut base_query, commitment);
+	base_query.push_str(" ORDER BY b.slot ASC");
+
+	Ok((base_query, vec![Box::new(slot)]))
+}
+
+/// Blocks produced by a validator identity with the rewards credited to it in each block
+fn build_validator_query(
+	identity: String,
+	from: Option<String>,
+	to: Option<String>,
+	commitment: Option<CommitmentFilter>,
+) -> AppResult<(String, Vec<QueryParams>)> {
+	let mut base_query = "SELECT b.blockhash, b.slot, b.block_time,
+		COALESCE(SUM(r.lamports), 0)::BIGINT AS reward_lamports
+		FROM blocks b
+		LEFT JOIN block_rewards r ON r.slot = b.slot AND r.pubkey = b.leader
+		WHERE b.leader = $1"
+		.to_string();
+	let mut query_params: Vec<QueryParams> = vec![Box::new(pubkey_bytes(&identity)?)];
 
-	base_query.push_str(" ORDER BY signature ASC");
+	add_date_conditions(&mut base_query, &mut query_params, &from, &to);
+	add_commitment_condition(&mut base_query, commitment);
 
-	(base_query, query_params)
+	base_query.push_str(" GROUP BY b.slot ORDER BY b.slot ASC");
+
+	Ok((base_query, query_params))
 }
 
 /// Converts a String date of format YYYY-MM-DD to Unix time and inserts it into a query
@@ -107,22 +395,49 @@ fn add_date_conditions(
 	to: &Option<String>,
 ) {
 	if let Some(from) = parse_date_opt(from) {
-		base_query.push_str(" block_time >= $1");
 		query_params.push(Box::new(from));
+		base_query.push_str(&format!(" AND b.block_time >= ${}", query_params.len()));
 	}
 	if let Some(to) = parse_date_opt(to) {
-		if !query_params.is_empty() {
-			base_query.push_str(" AND");
-		}
-		base_query.push_str(" block_time <= $2");
 		query_params.push(Box::new(to));
+		base_query.push_str(&format!(" AND b.block_time <= ${}", query_params.len()));
+	}
+}
+
+/// Limits results to finalized blocks, confirmed includes every stored block
+fn add_commitment_condition(base_query: &mut String, commitment: Option<CommitmentFilter>) {
+	if commitment == Some(CommitmentFilter::Finalized) {
+		base_query.push_str(" AND b.commitment = 'finalized'");
+	}
+}
+
+/// Limits results to successful or failed transactions, transactions stored without meta have no
+/// fee and an unknown status so they match neither
+fn add_status_condition(base_query: &mut String, status: Option<StatusFilter>) {
+	match status {
+		Some(StatusFilter::Success) => {
+			base_query.push_str(" AND t.err IS NULL AND t.fee IS NOT NULL")
+		}
+		Some(StatusFilter::Failed) => base_query.push_str(" AND t.err IS NOT NULL"),
+		None => {}
+	}
+}
+
+/// Limits results to transactions where the account had the given role
+fn add_role_condition(base_query: &mut String, role: Option<RoleFilter>) {
+	match role {
+		Some(RoleFilter::Signer) => base_query.push_str(" AND a.is_signer"),
+		Some(RoleFilter::Writable) => base_query.push_str(" AND a.is_writable"),
+		None => {}
 	}
 }
 
 /// Parses optional date
 fn parse_date_opt(date_str: &Option<String>) -> Option<i64> {
 	if let Some(date_str) = date_str {
-		let naive_date = NaiveDateTime::parse_from_str(&date_str, "%Y-%m-%d").ok()?;
+		let naive_date = NaiveDate::parse_from_str(date_str, "%Y-%m-%d")
+			.ok()?
+			.and_hms_opt(0, 0, 0)?;
 		let datetime = Utc.from_utc_datetime(&naive_date).timestamp();
 		Some(datetime)
 	} else {
@@ -132,5 +447,134 @@ fn parse_date_opt(date_str: &Option<String>) -> Option<i64> {
 
 #[cfg(test)]
 mod tests {
-    
+	use super::*;
+
+	#[test]
+	fn test_account_query_date_params() {
+		let (query, params) = build_query(
+			QueryType::Account {
+				pubkey: "11111111111111111111111111111111".to_string(),
+				from: Some("2024-01-01".to_string()),
+				to: Some("2024-02-01".to_string()),
+				status: Some(StatusFilter::Failed),
+				role: Some(RoleFilter::Signer),
+			},
+			None,
+		)
+		.unwrap();
+
+		assert_eq!(params.len(), 3);
+		assert!(query.contains("a.account = $1"));
+		assert!(query.contains("b.block_time >= $2"));
+		assert!(query.contains("b.block_time <= $3"));
+		assert!(query.contains("t.err IS NOT NULL"));
+		assert!(query.contains("a.is_signer"));
+	}
+
+	#[test]
+	fn test_success_status_excludes_unknown_status() {
+		let (query, _) = build_query(
+			QueryType::Account {
+				pubkey: "11111111111111111111111111111111".to_string(),
+				from: None,
+				to: None,
+				status: Some(StatusFilter::Success),
+				role: None,
+			},
+			None,
+		)
+		.unwrap();
+
+		assert!(query.contains("t.err IS NULL AND t.fee IS NOT NULL"));
+	}
+
+	#[test]
+	fn test_token_transfers_mint_param() {
+		let (query, params) = build_query(
+			QueryType::TokenTransfers {
+				pubkey: "11111111111111111111111111111111".to_string(),
+				from: Some("2024-01-01".to_string()),
+				to: None,
+				mint: Some("So11111111111111111111111111111111111111112".to_string()),
+			},
+			None,
+		)
+		.unwrap();
+
+		assert_eq!(params.len(), 3);
+		assert!(query.contains("FROM token_balance_changes c"));
+		assert!(query.contains("c.mint = $2"));
+		assert!(query.contains("b.block_time >= $3"));
+	}
+
+	#[test]
+	fn test_balances_query_skips_missing_balances() {
+		let (query, params) = build_query(
+			QueryType::Balances {
+				pubkey: "11111111111111111111111111111111".to_string(),
+				from: None,
+				to: Some("2024-02-01".to_string()),
+			},
+			None,
+		)
+		.unwrap();
+
+		assert_eq!(params.len(), 2);
+		assert!(query.contains("a.pre_balance IS NOT NULL"));
+		assert!(query.contains("b.block_time <= $2"));
+	}
+
+	#[test]
+	fn test_program_query_date_params() {
+		let (query, params) = build_query(
+			QueryType::Program {
+				program_id: "11111111111111111111111111111111".to_string(),
+				from: Some("2024-01-01".to_string()),
+				to: Some("2024-02-01".to_string()),
+			},
+			None,
+		)
+		.unwrap();
+
+		assert_eq!(params.len(), 3);
+		assert!(query.contains("FROM instructions i"));
+		assert!(query.contains("b.block_time >= $2"));
+		assert!(query.contains("b.block_time <= $3"));
+	}
+
+	#[test]
+	fn test_finalized_commitment_condition() {
+		let (query, _) = build_query(
+			QueryType::Block("11111111111111111111111111111111".to_string()),
+			Some(CommitmentFilter::Finalized),
+		)
+		.unwrap();
+
+		assert!(query.contains("b.commitment = 'finalized' ORDER BY"));
+	}
+
+	#[test]
+	fn test_block_query_keeps_blocks_without_transactions() {
+		let (query, _) = build_query(QueryType::Slot(10), None).unwrap();
+
+		assert!(query.contains("FROM blocks b"));
+		assert!(query.contains("LEFT JOIN transactions t"));
+		assert!(query.contains("LEFT JOIN transaction_accounts a"));
+	}
+
+	#[test]
+	fn test_invalid_pubkey() {
+		let result = build_query(
+			QueryType::Account {
+				pubkey: "not a pubkey".to_string(),
+				from: None,
+				to: None,
+				status: None,
+				role: None,
+			},
+			None,
+		);
+
+		assert!(result.is_err());
+	}
 }
@@ -35,8 +35,18 @@ impl Server {
             .route("/", get(root))
             .route("/api/transaction/:signature", get(transaction_handler))
             .route("/api/account/:pubkey", get(account_handler))
+            .route("/api/account/:pubkey/balances", get(balances_handler))
+            .route(
+                "/api/account/:pubkey/token-transfers",
+                get(token_transfers_handler),
+            )
             .route("/api/block/:blockhash", get(block_handler))
             .route("/api/slot/:slot_number", get(slot_handler))
+            .route("/api/program/:program_id", get(program_handler))
+            .route(
+                "/api/validator/:identity/blocks",
+                get(validator_blocks_handler),
+            )
             .fallback(handler_404)
             .layer(cors)
             .with_state(conn_pool);
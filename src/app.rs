@@ -0,0 +1,244 @@
+#[cfg(feature = "geyser")]
+use crate::client::geyser::GeyserSource;
+use crate::{
+	api::server::Server,
+	backfill::{resume_from_checkpoint, Backfiller, Checkpointer},
+	channel::stage_channel,
+	client::{
+		endpoints::EndpointPool, get_transaction::WatchedSignature, rpc_worker::RpcWorkerManager,
+		subscribe_logs::LogsWatcher, ws::WsClient,
+	},
+	config::{Config, IngestionMode, OverflowPolicy, StageConfig},
+	database::create_database_pool,
+	error::Result,
+	finalizer::Finalizer,
+	models::ProcessedBlock,
+	processor::{ProcessingContext, ProcessingWorkerManager},
+	storage::StorageWorkerManager,
+};
+use log::info;
+use solana_client::{
+	pubsub_client::{BlockSubscription, SlotsSubscription},
+	rpc_response::SlotInfo,
+};
+use solana_transaction_status::UiConfirmedBlock;
+use std::sync::Arc;
+
+/// Runs the ingestion pipeline, the background tasks and the api server until ctrl+c is
+/// received. `context` is shared by the processing workers, register decoders for other programs
+/// on its `DecoderRegistry` to index their instructions:
+///
+/// ```no_run
+/// use solder::{
+///     config::load_config,
+///     error::Result,
+///     models::{DecodedInstruction, ProcessedInstruction},
+///     processor::{DecoderRegistry, InstructionDecoder, ProcessingContext},
+/// };
+///
+/// struct MyProgramDecoder;
+///
+/// impl InstructionDecoder for MyProgramDecoder {
+///     fn decode(&self, instruction: &ProcessedInstruction) -> Result<DecodedInstruction> {
+///         Ok(DecodedInstruction::new(
+///             "my-program",
+///             "unknown",
+///             serde_json::json!({ "data_len": instruction.data.len() }),
+///         ))
+///     }
+/// }
+///
+/// #[tokio::main]
+/// async fn main() -> Result<()> {
+///     let config = load_config("Config.toml")?;
+///     let mut decoders = DecoderRegistry::with_builtin();
+///     decoders.register("<program id>", MyProgramDecoder);
+///
+///     solder::app::run(config, ProcessingContext::new(decoders)).await
+/// }
+/// ```
+pub async fn run(config: Config, context: ProcessingContext) -> Result<()> {
+	// Every RPC consumer shares the endpoints so that their rate limits and health hold across
+	// the whole process
+	let endpoints = Arc::new(EndpointPool::new(&config.client));
+
+	let (rpc_tx, rpc_rx) = stage_channel::<SlotInfo>("RPC", &config.channels.rpc);
+	let (proc_tx, proc_rx) =
+		stage_channel::<(SlotInfo, UiConfirmedBlock)>("PROCESSING", &config.channels.processor);
+	let (storage_tx, storage_rx) =
+		stage_channel::<ProcessedBlock>("STORAGE", &config.channels.storage);
+
+	let backfill_tx = rpc_tx.clone();
+	// Catch up slots have their own queue and rpc workers so that live slots are neither held
+	// back by them nor drop them from the rpc queue
+	let (resume_tx, resume_rx) = stage_channel::<SlotInfo>(
+		"RESUME",
+		&StageConfig {
+			overflow: OverflowPolicy::Block,
+			..config.channels.rpc.clone()
+		},
+	);
+	// In blocks and geyser mode blocks are sent to the processing workers directly, the rpc
+	// workers are still used by resume and backfill
+	let ingestion = config.client.ingestion;
+	let ws_client_config = config.client.clone();
+	let block_tx = proc_tx.clone();
+	#[cfg(feature = "geyser")]
+	let geyser_config = config.geyser.clone();
+
+	info!("Creating db_pool");
+	let db_pool = create_database_pool(&config.database).await?;
+
+	// In logs mode watched signatures are fetched by their own rpc workers
+	let (watch_tx, watch_rx) = stage_channel::<WatchedSignature>("WATCHER", &config.channels.rpc);
+	let logs_watcher = LogsWatcher::new(config.client.clone(), config.watcher.clone(), watch_tx);
+	let watch_wm = (ingestion == IngestionMode::Logs).then(|| {
+		RpcWorkerManager::<WatchedSignature>::new(
+			&config.client,
+			endpoints.clone(),
+			db_pool.clone(),
+			watch_rx,
+			proc_tx.clone(),
+			config.watcher.worker_threads as usize,
+		)
+	});
+
+	let backfiller = Backfiller::new(
+		config.backfill.clone(),
+		endpoints.clone(),
+		db_pool.clone(),
+		backfill_tx,
+	);
+
+	let finalizer = Finalizer::new(config.finalizer.clone(), endpoints.clone(), db_pool.clone());
+
+	// Logs mode only indexes watched transactions so full blocks are neither checkpointed nor
+	// caught up
+	let checkpointer = (ingestion != IngestionMode::Logs).then(|| {
+		Checkpointer::new(
+			config.resume.checkpoint_interval_secs,
+			endpoints.clone(),
+			db_pool.clone(),
+		)
+	});
+	let resume_config = config.resume.clone();
+	let resume_endpoints = endpoints.clone();
+	let resume_db_pool = db_pool.clone();
+	let resume_wm = (config.resume.enabled && ingestion != IngestionMode::Logs).then(|| {
+		RpcWorkerManager::<SlotInfo>::new(
+			&config.client,
+			endpoints.clone(),
+			db_pool.clone(),
+			resume_rx,
+			proc_tx.clone(),
+			config.resume.worker_threads as usize,
+		)
+	});
+
+	info!("Creating rpc_wm");
+	let client_threads = config.client.worker_threads;
+	let mut rpc_wm = RpcWorkerManager::<SlotInfo>::new(
+		&config.client,
+		endpoints.clone(),
+		db_pool.clone(),
+		rpc_rx,
+		proc_tx,
+		client_threads as usize,
+	);
+
+	info!("Creating proc_wm");
+	let mut proc_wm = ProcessingWorkerManager::new(
+		proc_rx,
+		storage_tx,
+		config.processor.worker_threads as usize,
+		context,
+	);
+
+	info!("Creating storage_wm");
+	let storage_wm = StorageWorkerManager::new(config.storage, db_pool.clone(), storage_rx);
+
+	info!("Creating server");
+	let server = Server::new(db_pool.clone(), config.server.port);
+
+	info!("Starting {:?} subscription", ingestion);
+	let _ws_handle = tokio::spawn(async move {
+		let result = match ingestion {
+			IngestionMode::Slots => {
+				WsClient::<SlotsSubscription>::new(ws_client_config, rpc_tx)
+					.subscribe()
+					.await
+			}
+			IngestionMode::Blocks => {
+				WsClient::<BlockSubscription, _>::new(ws_client_config, block_tx)
+					.subscribe()
+					.await
+			}
+			IngestionMode::Logs => logs_watcher.subscribe().await,
+			#[cfg(feature = "geyser")]
+			IngestionMode::Geyser => GeyserSource::new(geyser_config, block_tx).subscribe().await,
+			#[cfg(not(feature = "geyser"))]
+			IngestionMode::Geyser => Err(crate::error::AppError::GeyserError(
+				"Built without the geyser feature".to_string(),
+			)),
+		};
+		if let Err(e) = result {
+			log::error!("{:?} ingestion stopped: {}", ingestion, e);
+		}
+	});
+
+	let _metrics_handle = tokio::spawn(endpoints.clone().report_metrics());
+
+	// Catch up on slots missed since the last run alongside live slots
+	if let Some(mut resume_wm) = resume_wm {
+		info!("Starting resume");
+		let _resume_handle = tokio::spawn(async move {
+			if let Err(e) = resume_from_checkpoint(
+				&resume_config,
+				&resume_endpoints,
+				&resume_db_pool,
+				resume_tx,
+			)
+			.await
+			{
+				log::error!("Could not resume from checkpoint: {}", e);
+			}
+		});
+		let _resume_wm_handle = tokio::spawn(async move { resume_wm.run().await });
+	}
+
+	if let Some(checkpointer) = checkpointer {
+		info!("Starting checkpoint");
+		let _checkpoint_handle = tokio::spawn(async move { checkpointer.run().await });
+	}
+
+	info!("Starting rpc_wm");
+	let _rpc_handle = tokio::spawn(async move { rpc_wm.run().await });
+
+	if let Some(mut watch_wm) = watch_wm {
+		info!("Starting watch_wm");
+		let _watch_handle = tokio::spawn(async move { watch_wm.run().await });
+	}
+
+	info!("Starting proc_wm");
+	let _proc_handle = tokio::spawn(async move { proc_wm.run().await });
+
+	info!("Starting storage_wm");
+	let _db_handle = tokio::spawn(async move { storage_wm.await.run().await });
+
+	if config.backfill.enabled {
+		info!("Starting backfill");
+		let _backfill_handle = tokio::spawn(async move { backfiller.run().await });
+	}
+
+	if config.finalizer.enabled {
+		info!("Starting finalizer");
+		let _finalizer_handle = tokio::spawn(async move { finalizer.run().await });
+	}
+
+	info!("Running server");
+	let _server_handle = tokio::spawn(async move { server.await.run().await });
+
+	tokio::signal::ctrl_c().await?;
+
+	Ok(())
+}
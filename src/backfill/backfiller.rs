@@ -0,0 +1,249 @@
+use super::{find_gaps, record_gaps};
+use crate::{
+	channel::StageSender,
+	client::endpoints::EndpointPool,
+	config::BackfillConfig,
+	database::DatabasePool,
+	error::{AppError, Result},
+};
+use solana_client::rpc_response::SlotInfo;
+use solana_sdk::commitment_config::CommitmentConfig;
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::time::{interval, Duration};
+
+/// Periodically scans stored slots for holes left by websocket reconnects, failed RPC calls
+/// or downtime. Slots with a produced block are pushed back into the rpc queue so that they go
+/// through the same `Gettable` pipeline as live slots, slots skipped by the leader are recorded
+/// in `slot_gaps` so they are not checked again. Stored blocks are also checked against their
+/// parents, a parent that is not stored is missing and a parent whose blockhash differs from the
+/// child's `previous_blockhash` means blocks of different forks were stored.
+pub struct Backfiller {
+	config: BackfillConfig,
+	endpoints: Arc<EndpointPool>,
+	db_pool: DatabasePool,
+	rpc_tx: StageSender<SlotInfo>,
+}
+
+impl Backfiller {
+	pub fn new(
+		config: BackfillConfig,
+		endpoints: Arc<EndpointPool>,
+		db_pool: DatabasePool,
+		rpc_tx: StageSender<SlotInfo>,
+	) -> Self {
+		Self {
+			config,
+			endpoints,
+			db_pool,
+			rpc_tx,
+		}
+	}
+
+	/// Runs a scan every `interval_secs` until ctrl+c is received
+	pub async fn run(self) -> Result<()> {
+		let mut ticker = interval(Duration::from_secs(self.config.interval_secs));
+		let mut cursor = self.config.start_slot;
+
+		loop {
+			tokio::select! {
+				_ = ticker.tick() => {
+					match self.scan(cursor).await {
+						Ok(next) => cursor = next,
+						Err(e) => log::error!("[BACKFILL] Scan failed: {}", e),
+					}
+				}
+				_ = tokio::signal::ctrl_c() => {
+					log::info!("[BACKFILL] Received Ctrl+C signal. Stopping backfill...");
+					break;
+				}
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Scans at most `batch_size` slots from `cursor` and returns the cursor for the next scan.
+	/// Previously detected missing slots are retried first.
+	async fn scan(&self, cursor: Option<u64>) -> Result<Option<u64>> {
+		self.retry_missing().await?;
+
+		let start = match cursor {
+			Some(start) => start,
+			None => match self.lowest_stored_slot().await? {
+				Some(start) => start,
+				None => {
+					log::debug!("[BACKFILL] No stored slots, nothing to scan");
+					return Ok(None);
+				}
+			},
+		};
+
+		// Stay clear of the tip so that slots still in flight through the live pipeline are
+		// not reported as gaps
+		let tip = self
+			.endpoints
+			.with_client(|client| async move { client.get_slot().await })
+			.await?
+			.saturating_sub(self.config.slot_margin);
+		if start > tip {
+			return Ok(Some(start));
+		}
+		let end = tip.min(start + self.config.batch_size.max(1) - 1);
+
+		let produced = self
+			.endpoints
+			.with_client(|client| async move {
+				client
+					.get_blocks_with_commitment(start, Some(end), CommitmentConfig::confirmed())
+					.await
+			})
+			.await?;
+		let stored = self.stored_slots(start, end).await?;
+		let mut gaps = find_gaps(start, end, &produced, &stored);
+		for parent in self.unlinked_parents(start, end).await? {
+			if !gaps.missing.contains(&parent) {
+				gaps.missing.push(parent);
+			}
+		}
+		gaps.skipped.retain(|slot| !gaps.missing.contains(slot));
+		self.check_previous_blockhashes(start, end).await?;
+
+		log::info!(
+			"[BACKFILL] Scanned slots {}-{}: {} missing, {} skipped",
+			start,
+			end,
+			gaps.missing.len(),
+			gaps.skipped.len()
+		);
+
+		record_gaps(&self.db_pool, &gaps.skipped, "skipped").await?;
+		record_gaps(&self.db_pool, &gaps.missing, "missing").await?;
+		self.enqueue(gaps.missing).await?;
+
+		Ok(Some(end + 1))
+	}
+
+	/// Removes missing slots that have since been stored and re-enqueues the remainder until
+	/// `max_attempts` is reached
+	async fn retry_missing(&self) -> Result<()> {
+		let conn = self.db_pool.get().await?;
+
+		conn.execute(
+			"DELETE FROM slot_gaps g
+			WHERE g.status = 'missing'
+			AND EXISTS (SELECT 1 FROM blocks b WHERE b.slot = g.slot AND b.blockhash IS NOT NULL)",
+			&[],
+		)
+		.await?;
+
+		let rows = conn
+			.query(
+				"UPDATE slot_gaps SET attempts = attempts + 1
+				WHERE status = 'missing' AND attempts < $1
+				RETURNING slot",
+				&[&self.config.max_attempts],
+			)
+			.await?;
+
+		let slots = rows
+			.into_iter()
+			.map(|row| row.get::<_, i64>("slot") as u64)
+			.collect();
+		self.enqueue(slots).await
+	}
+
+	async fn lowest_stored_slot(&self) -> Result<Option<u64>> {
+		let conn = self.db_pool.get().await?;
+		let row = conn
+			.query_one("SELECT MIN(slot) AS slot FROM blocks", &[])
+			.await?;
+		let slot: Option<i64> = row.get("slot");
+
+		Ok(slot.map(|slot| slot as u64))
+	}
+
+	/// Slots whose full block is stored, blocks stored without a header from a watched
+	/// transaction are still missing
+	async fn stored_slots(&self, start: u64, end: u64) -> Result<HashSet<u64>> {
+		let conn = self.db_pool.get().await?;
+		let rows = conn
+			.query(
+				"SELECT slot FROM blocks
+				WHERE slot BETWEEN $1 AND $2 AND blockhash IS NOT NULL",
+				&[&(start as i64), &(end as i64)],
+			)
+			.await?;
+
+		Ok(rows
+			.into_iter()
+			.map(|row| row.get::<_, i64>("slot") as u64)
+			.collect())
+	}
+
+	/// Parents of stored blocks within the range that are not stored themselves, parents below
+	/// `start` are left to the scan that covers them
+	async fn unlinked_parents(&self, start: u64, end: u64) -> Result<Vec<u64>> {
+		let conn = self.db_pool.get().await?;
+		let rows = conn
+			.query(
+				"SELECT DISTINCT b.parent_slot FROM blocks b
+				WHERE b.slot BETWEEN $1 AND $2
+				AND b.parent_slot >= $1
+				AND NOT EXISTS (
+					SELECT 1 FROM blocks p WHERE p.slot = b.parent_slot AND p.blockhash IS NOT NULL
+				)",
+				&[&(start as i64), &(end as i64)],
+			)
+			.await?;
+
+		Ok(rows
+			.into_iter()
+			.map(|row| row.get::<_, i64>("parent_slot") as u64)
+			.collect())
+	}
+
+	/// Logs stored blocks whose `previous_blockhash` does not match the stored parent
+	async fn check_previous_blockhashes(&self, start: u64, end: u64) -> Result<()> {
+		let conn = self.db_pool.get().await?;
+		let rows = conn
+			.query(
+				"SELECT b.slot FROM blocks b
+				JOIN blocks p ON p.slot = b.parent_slot
+				WHERE b.slot BETWEEN $1 AND $2
+				AND b.previous_blockhash <> p.blockhash",
+				&[&(start as i64), &(end as i64)],
+			)
+			.await?;
+
+		for row in rows {
+			log::warn!(
+				"[BACKFILL] Block {} does not link to the stored parent block",
+				row.get::<_, i64>("slot")
+			);
+		}
+
+		Ok(())
+	}
+
+	/// Pushes `slots` into the rpc queue waiting for room, so that gap slots are not dropped by
+	/// the queue's overflow policy. The wait runs on a blocking thread.
+	async fn enqueue(&self, slots: Vec<u64>) -> Result<()> {
+		if slots.is_empty() {
+			return Ok(());
+		}
+
+		let rpc_tx = self.rpc_tx.clone();
+		tokio::task::spawn_blocking(move || {
+			for slot in slots {
+				rpc_tx.send_wait(SlotInfo {
+					slot,
+					parent: slot.saturating_sub(1),
+					root: 0,
+				})?;
+			}
+			Ok::<(), AppError>(())
+		})
+		.await?
+	}
+}
@@ -0,0 +1,154 @@
+use super::resume::{last_committed_slot, stored_slots};
+use crate::{client::endpoints::EndpointPool, database::DatabasePool, error::Result};
+use solana_sdk::commitment_config::CommitmentConfig;
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::time::{interval, Duration};
+
+/// Most slots the checkpoint is checked past in one run
+const CHECKPOINT_RANGE: u64 = 10_000;
+
+/// Periodically advances the checkpoint of the storage workers in `ingestion_state`. Blocks are
+/// committed out of order, so the checkpoint is a low-water mark: the blocks produced after it
+/// (`getBlocks`) are walked in order and it stops before the first one that is not stored. Slots
+/// skipped by their leader are not returned by `getBlocks` and are stepped over.
+pub struct Checkpointer {
+	interval_secs: u64,
+	endpoints: Arc<EndpointPool>,
+	db_pool: DatabasePool,
+}
+
+impl Checkpointer {
+	pub fn new(interval_secs: u64, endpoints: Arc<EndpointPool>, db_pool: DatabasePool) -> Self {
+		Self {
+			interval_secs,
+			endpoints,
+			db_pool,
+		}
+	}
+
+	/// Advances the checkpoint every `interval_secs` until ctrl+c is received
+	pub async fn run(self) -> Result<()> {
+		let mut ticker = interval(Duration::from_secs(self.interval_secs));
+
+		loop {
+			tokio::select! {
+				_ = ticker.tick() => {
+					if let Err(e) = self.advance().await {
+						log::error!("[CHECKPOINT] Could not advance checkpoint: {}", e);
+					}
+				}
+				_ = tokio::signal::ctrl_c() => {
+					log::info!("[CHECKPOINT] Received Ctrl+C signal. Stopping checkpoint...");
+					break;
+				}
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Moves the checkpoint past every produced block within `CHECKPOINT_RANGE` slots that is
+	/// stored. Without a checkpoint it starts at the lowest stored block.
+	async fn advance(&self) -> Result<()> {
+		let checkpoint = match last_committed_slot(&self.db_pool).await? {
+			Some(slot) => slot,
+			None => match self.start_checkpoint().await? {
+				Some(slot) => slot,
+				None => return Ok(()),
+			},
+		};
+
+		let tip = self
+			.endpoints
+			.with_client(|client| async move {
+				client
+					.get_slot_with_commitment(CommitmentConfig::confirmed())
+					.await
+			})
+			.await?;
+		let start = checkpoint + 1;
+		if start > tip {
+			return Ok(());
+		}
+		let end = tip.min(checkpoint + CHECKPOINT_RANGE);
+
+		let produced = self
+			.endpoints
+			.with_client(|client| async move {
+				client
+					.get_blocks_with_commitment(start, Some(end), CommitmentConfig::confirmed())
+					.await
+			})
+			.await?;
+		let stored = stored_slots(&self.db_pool, start, end).await?;
+		let next = next_checkpoint(end, &produced, &stored);
+		if next == checkpoint {
+			return Ok(());
+		}
+
+		let conn = self.db_pool.get().await?;
+		conn.execute(
+			"UPDATE ingestion_state
+			SET slot = GREATEST(slot, $1), updated_at = EXTRACT(EPOCH FROM NOW())::BIGINT
+			WHERE stage = 'storage'",
+			&[&(next as i64)],
+		)
+		.await?;
+		log::debug!("[CHECKPOINT] Advanced checkpoint to slot {}", next);
+
+		Ok(())
+	}
+
+	/// Creates the checkpoint at the lowest stored block, `None` if no block is stored yet
+	async fn start_checkpoint(&self) -> Result<Option<u64>> {
+		let conn = self.db_pool.get().await?;
+		let row = conn
+			.query_one(
+				"SELECT MIN(slot) AS slot FROM blocks WHERE blockhash IS NOT NULL",
+				&[],
+			)
+			.await?;
+		let slot: i64 = match row.get("slot") {
+			Some(slot) => slot,
+			None => return Ok(None),
+		};
+
+		conn.execute(
+			"INSERT INTO ingestion_state (stage, slot) VALUES ('storage', $1)
+			ON CONFLICT (stage) DO NOTHING",
+			&[&slot],
+		)
+		.await?;
+		log::info!("[CHECKPOINT] Starting checkpoint at slot {}", slot);
+
+		last_committed_slot(&self.db_pool).await
+	}
+}
+
+/// Slot the checkpoint can move to given the produced slots after it up to `end`, the slot before
+/// the first produced block that is not stored or `end` if they are all stored
+fn next_checkpoint(end: u64, produced: &[u64], stored: &HashSet<u64>) -> u64 {
+	match produced.iter().find(|slot| !stored.contains(slot)) {
+		Some(missing) => missing - 1,
+		None => end,
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_next_checkpoint() {
+		let produced = vec![11, 12, 15, 16, 18];
+		let stored: HashSet<u64> = [11, 12, 15, 18].into_iter().collect();
+
+		// Skipped slots 13 and 14 are stepped over, 16 is not stored yet
+		assert_eq!(next_checkpoint(20, &produced, &stored), 15);
+
+		let stored: HashSet<u64> = produced.iter().copied().collect();
+		assert_eq!(next_checkpoint(20, &produced, &stored), 20);
+		assert_eq!(next_checkpoint(20, &produced, &HashSet::new()), 10);
+	}
+}
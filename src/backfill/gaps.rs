@@ -0,0 +1,65 @@
+use crate::{database::DatabasePool, error::Result};
+use std::collections::HashSet;
+
+/// Slots within a scanned range that are not in the database. `missing` slots had a block
+/// produced but were never fetched, `skipped` slots had no block produced by the leader.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Gaps {
+	pub missing: Vec<u64>,
+	pub skipped: Vec<u64>,
+}
+
+/// Compares the slots stored in the database against the slots with produced blocks as
+/// reported by `getBlocks` for the inclusive range `start..=end`.
+pub fn find_gaps(start: u64, end: u64, produced: &[u64], stored: &HashSet<u64>) -> Gaps {
+	let produced: HashSet<u64> = produced.iter().copied().collect();
+	let mut gaps = Gaps::default();
+
+	for slot in start..=end {
+		if stored.contains(&slot) {
+			continue;
+		}
+		if produced.contains(&slot) {
+			gaps.missing.push(slot);
+		} else {
+			gaps.skipped.push(slot);
+		}
+	}
+
+	gaps
+}
+
+/// Records `slots` in `slot_gaps` with `status`, slots already recorded are left as they are
+pub async fn record_gaps(db_pool: &DatabasePool, slots: &[u64], status: &str) -> Result<()> {
+	if slots.is_empty() {
+		return Ok(());
+	}
+
+	let conn = db_pool.get().await?;
+	let slots: Vec<i64> = slots.iter().map(|slot| *slot as i64).collect();
+	conn.execute(
+		"INSERT INTO slot_gaps (slot, status)
+		SELECT slot, $2::TEXT FROM UNNEST($1::BIGINT[]) AS slot
+		ON CONFLICT (slot) DO NOTHING",
+		&[&slots, &status],
+	)
+	.await?;
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_find_gaps() {
+		let produced = vec![10, 11, 13, 14, 15];
+		let stored: HashSet<u64> = [10, 14].into_iter().collect();
+
+		let gaps = find_gaps(10, 15, &produced, &stored);
+
+		assert_eq!(gaps.missing, vec![11, 13, 15]);
+		assert_eq!(gaps.skipped, vec![12]);
+	}
+}
@@ -0,0 +1,9 @@
+pub mod backfiller;
+pub mod checkpoint;
+pub mod gaps;
+pub mod resume;
+
+pub use backfiller::Backfiller;
+pub use checkpoint::Checkpointer;
+pub use gaps::{find_gaps, record_gaps, Gaps};
+pub use resume::resume_from_checkpoint;
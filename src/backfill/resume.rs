@@ -0,0 +1,113 @@
+use super::find_gaps;
+use crate::{
+	channel::StageSender,
+	client::endpoints::EndpointPool,
+	config::ResumeConfig,
+	database::DatabasePool,
+	error::{AppError, Result},
+};
+use solana_client::rpc_response::SlotInfo;
+use solana_sdk::commitment_config::CommitmentConfig;
+use std::collections::HashSet;
+
+/// Reads the checkpoint of the storage workers from `ingestion_state`, every produced block up
+/// to it is stored. The checkpoint is advanced by the `Checkpointer`
+pub async fn last_committed_slot(db_pool: &DatabasePool) -> Result<Option<u64>> {
+	let conn = db_pool.get().await?;
+	let row = conn
+		.query_opt(
+			"SELECT slot FROM ingestion_state WHERE stage = 'storage'",
+			&[],
+		)
+		.await?;
+
+	Ok(row.map(|row| row.get::<_, i64>("slot") as u64))
+}
+
+/// Pushes every produced block between the checkpoint and the current tip that is not stored yet
+/// into the resume queue. Runs alongside the live subscription, slots are sent without applying
+/// the channel's overflow policy so that the catch up range is never dropped.
+pub async fn resume_from_checkpoint(
+	config: &ResumeConfig,
+	endpoints: &EndpointPool,
+	db_pool: &DatabasePool,
+	rpc_tx: StageSender<SlotInfo>,
+) -> Result<()> {
+	let last_slot = match last_committed_slot(db_pool).await? {
+		Some(slot) => slot,
+		None => {
+			log::info!("[RESUME] No checkpoint found, starting from live slots");
+			return Ok(());
+		}
+	};
+
+	let tip = endpoints
+		.with_client(|client| async move { client.get_slot().await })
+		.await?;
+
+	let mut start = last_slot + 1;
+	if tip < start {
+		return Ok(());
+	}
+	if tip - start > config.max_slots {
+		let skipped_to = tip - config.max_slots;
+		log::warn!(
+			"[RESUME] {} slots behind, resuming from {} instead of {}, enable backfill to recover the rest",
+			tip - start,
+			skipped_to,
+			start
+		);
+		start = skipped_to;
+	}
+
+	let produced = endpoints
+		.with_client(|client| async move {
+			client
+				.get_blocks_with_commitment(start, Some(tip), CommitmentConfig::confirmed())
+				.await
+		})
+		.await?;
+	let stored = stored_slots(db_pool, start, tip).await?;
+	let missing = find_gaps(start, tip, &produced, &stored).missing;
+	log::info!(
+		"[RESUME] Resuming from slot {}, queueing {} blocks up to slot {}",
+		start,
+		missing.len(),
+		tip
+	);
+
+	tokio::task::spawn_blocking(move || {
+		for slot in missing {
+			rpc_tx.send_wait(SlotInfo {
+				slot,
+				parent: slot.saturating_sub(1),
+				root: 0,
+			})?;
+		}
+		Ok::<(), AppError>(())
+	})
+	.await??;
+
+	Ok(())
+}
+
+/// Blocks committed above the checkpoint before the last shutdown are not fetched again, blocks
+/// stored without a header from a watched transaction still are
+pub(super) async fn stored_slots(
+	db_pool: &DatabasePool,
+	start: u64,
+	end: u64,
+) -> Result<HashSet<u64>> {
+	let conn = db_pool.get().await?;
+	let rows = conn
+		.query(
+			"SELECT slot FROM blocks WHERE slot BETWEEN $1 AND $2 AND blockhash IS NOT NULL",
+			&[&(start as i64), &(end as i64)],
+		)
+		.await?;
+
+	Ok(rows
+		.into_iter()
+		.map(|row| row.get::<_, i64>("slot") as u64)
+		.collect())
+}
@@ -0,0 +1,206 @@
+use crate::config::{OverflowPolicy, StageConfig};
+use crate::error::{AppError, Result};
+use crossbeam_channel::{Receiver, SendTimeoutError, Sender, TrySendError};
+use std::ops::Deref;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How often a sender waiting for room checks whether every receiver was dropped
+const CLOSED_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Sending half of a channel between two pipeline stages. When the channel is bounded and full
+/// the stage's `OverflowPolicy` decides whether the sender blocks until a downstream worker
+/// catches up or the oldest queued item is discarded to make room. The sender keeps a receiver
+/// to discard items, sends fail once every `StageReceiver` was dropped.
+pub struct StageSender<T> {
+	stage: &'static str,
+	policy: OverflowPolicy,
+	tx: Sender<T>,
+	rx: Receiver<T>,
+	receivers: Arc<AtomicUsize>,
+}
+
+impl<T> Clone for StageSender<T> {
+	fn clone(&self) -> Self {
+		Self {
+			stage: self.stage,
+			policy: self.policy,
+			tx: self.tx.clone(),
+			rx: self.rx.clone(),
+			receivers: self.receivers.clone(),
+		}
+	}
+}
+
+/// Receiving half of a channel between two pipeline stages, dereferences to the underlying
+/// `Receiver`. Clones are counted so that senders see the channel close once the downstream
+/// workers are gone.
+pub struct StageReceiver<T> {
+	rx: Receiver<T>,
+	receivers: Arc<AtomicUsize>,
+}
+
+impl<T> Clone for StageReceiver<T> {
+	fn clone(&self) -> Self {
+		self.receivers.fetch_add(1, Ordering::SeqCst);
+		Self {
+			rx: self.rx.clone(),
+			receivers: self.receivers.clone(),
+		}
+	}
+}
+
+impl<T> Drop for StageReceiver<T> {
+	fn drop(&mut self) {
+		self.receivers.fetch_sub(1, Ordering::SeqCst);
+	}
+}
+
+impl<T> Deref for StageReceiver<T> {
+	type Target = Receiver<T>;
+
+	fn deref(&self) -> &Self::Target {
+		&self.rx
+	}
+}
+
+/// Creates the channel for a pipeline stage, a capacity of zero creates an unbounded channel
+pub fn stage_channel<T>(
+	stage: &'static str,
+	config: &StageConfig,
+) -> (StageSender<T>, StageReceiver<T>) {
+	let (tx, rx) = if config.capacity == 0 {
+		crossbeam_channel::unbounded()
+	} else {
+		crossbeam_channel::bounded(config.capacity)
+	};
+	let receivers = Arc::new(AtomicUsize::new(1));
+
+	(
+		StageSender {
+			stage,
+			policy: config.overflow,
+			tx,
+			rx: rx.clone(),
+			receivers: receivers.clone(),
+		},
+		StageReceiver { rx, receivers },
+	)
+}
+
+impl<T> StageSender<T> {
+	/// Sends an item downstream applying the overflow policy if the channel is full. Under
+	/// `OverflowPolicy::Block` this blocks the thread, async code uses `send_async`.
+	pub fn send(&self, item: T) -> Result<()> {
+		match self.policy {
+			OverflowPolicy::Block => self.send_wait(item),
+			OverflowPolicy::DropOldest => {
+				let mut item = item;
+				loop {
+					if self.is_closed() {
+						return Err(AppError::ChannelSendError);
+					}
+					match self.tx.try_send(item) {
+						Ok(()) => return Ok(()),
+						Err(TrySendError::Full(rejected)) => {
+							if self.rx.try_recv().is_ok() {
+								log::warn!("[{}] Queue full, dropped oldest item", self.stage);
+							}
+							item = rejected;
+						}
+						Err(TrySendError::Disconnected(_)) => {
+							return Err(AppError::ChannelSendError)
+						}
+					}
+				}
+			}
+		}
+	}
+
+	/// Sends an item downstream waiting for room regardless of the overflow policy, for items
+	/// that must not be dropped. Blocks the thread while the channel is full.
+	pub fn send_wait(&self, item: T) -> Result<()> {
+		let mut item = item;
+		loop {
+			if self.is_closed() {
+				return Err(AppError::ChannelSendError);
+			}
+			match self.tx.send_timeout(item, CLOSED_CHECK_INTERVAL) {
+				Ok(()) => return Ok(()),
+				Err(SendTimeoutError::Timeout(rejected)) => item = rejected,
+				Err(SendTimeoutError::Disconnected(_)) => return Err(AppError::ChannelSendError),
+			}
+		}
+	}
+
+	/// Number of items waiting in the channel
+	pub fn len(&self) -> usize {
+		self.tx.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.tx.is_empty()
+	}
+
+	/// Whether every receiver was dropped
+	pub fn is_closed(&self) -> bool {
+		self.receivers.load(Ordering::SeqCst) == 0
+	}
+}
+
+impl<T: Send + 'static> StageSender<T> {
+	/// Like `send` for async code, waiting for room under `OverflowPolicy::Block` happens on a
+	/// blocking thread so that the runtime's threads are not held
+	pub async fn send_async(&self, item: T) -> Result<()> {
+		if self.policy == OverflowPolicy::DropOldest {
+			return self.send(item);
+		}
+
+		match self.tx.try_send(item) {
+			Ok(()) => Ok(()),
+			Err(TrySendError::Full(item)) => {
+				let tx = self.clone();
+				tokio::task::spawn_blocking(move || tx.send_wait(item)).await?
+			}
+			Err(TrySendError::Disconnected(_)) => Err(AppError::ChannelSendError),
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_drop_oldest() {
+		let config = StageConfig {
+			capacity: 2,
+			overflow: OverflowPolicy::DropOldest,
+		};
+		let (tx, rx) = stage_channel::<u64>("test", &config);
+
+		for slot in 0..5 {
+			tx.send(slot).unwrap();
+		}
+
+		assert_eq!(rx.try_iter().collect::<Vec<_>>(), vec![3, 4]);
+	}
+
+	#[test]
+	fn test_send_fails_once_receivers_are_dropped() {
+		let config = StageConfig {
+			capacity: 1,
+			overflow: OverflowPolicy::Block,
+		};
+		let (tx, rx) = stage_channel::<u64>("test", &config);
+		let worker_rx = rx.clone();
+
+		tx.send(0).unwrap();
+		drop(rx);
+		assert_eq!(worker_rx.recv().unwrap(), 0);
+		drop(worker_rx);
+
+		assert!(matches!(tx.send(1), Err(AppError::ChannelSendError)));
+	}
+}
@@ -0,0 +1,409 @@
+use super::{rate_limiter::RateLimiter, retry::classify_error, rpc_sender::RateLimitedSender};
+use crate::{
+	config::{ClientConfig, FailoverConfig},
+	error::{AppError, Result},
+};
+use rand::Rng;
+use solana_client::{
+	client_error::{ClientErrorKind, Result as ClientResult},
+	nonblocking::rpc_client::RpcClient,
+	rpc_client::RpcClientConfig,
+};
+use solana_sdk::commitment_config::CommitmentConfig;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::time::interval;
+
+/// Smoothing factor of the latency and error rate averages, recent requests weigh the most
+const EWMA_ALPHA: f64 = 0.2;
+
+/// Lowest health an endpoint falls to so that it still receives the odd request to recover
+const MIN_HEALTH: f64 = 0.05;
+
+/// Why a request to an endpoint failed. Rate limits and timeouts put the endpoint in cooldown
+/// straight away, other errors only once they repeat.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FailureKind {
+	RateLimited,
+	Timeout,
+	Error,
+}
+
+impl FailureKind {
+	/// Classifies an error of `Gettable::get`, returns `None` for errors returned by the RPC
+	/// node itself such as a skipped slot as another endpoint would return the same
+	pub fn from_error(error: &AppError) -> Option<Self> {
+		match error {
+			AppError::RateLimited(_) => Some(Self::RateLimited),
+			AppError::TimeoutError => Some(Self::Timeout),
+			AppError::SolanaClientError(e) => match e.kind() {
+				ClientErrorKind::RpcError(_) => None,
+				ClientErrorKind::Reqwest(e) if e.is_timeout() => Some(Self::Timeout),
+				_ => Some(Self::Error),
+			},
+			_ => None,
+		}
+	}
+}
+
+#[derive(Default)]
+struct EndpointStats {
+	latency_ms: Option<f64>,
+	error_rate: f64,
+	consecutive_failures: u32,
+	cooldown_until: Option<Instant>,
+	requests: u64,
+	failures: u64,
+	rate_limited: u64,
+	timeouts: u64,
+}
+
+impl EndpointStats {
+	fn cooling_down(&self, now: Instant) -> bool {
+		self.cooldown_until.is_some_and(|until| until > now)
+	}
+
+	/// Between `MIN_HEALTH` and 1, lowered by errors and by latency above a second
+	fn health(&self) -> f64 {
+		let latency_penalty = 1.0 + self.latency_ms.unwrap_or(0.0) / 1000.0;
+		((1.0 - self.error_rate) / latency_penalty).max(MIN_HEALTH)
+	}
+}
+
+/// RPC endpoint passed to `Gettable::get`. The client is created once and shared by every rpc
+/// worker so that connections to the endpoint are kept alive between requests, requests go
+/// through the endpoint's `RateLimiter`.
+pub struct Endpoint {
+	pub config: ClientConfig,
+	pub client: Arc<RpcClient>,
+	pub limiter: Arc<RateLimiter>,
+	weight: u32,
+	stats: Mutex<EndpointStats>,
+}
+
+impl Endpoint {
+	fn new(config: ClientConfig, weight: u32) -> Self {
+		let limiter = Arc::new(RateLimiter::new(
+			config.rate_limit.requests_per_sec,
+			config.rate_limit.burst,
+		));
+		let sender = RateLimitedSender::new(
+			config.get_url(),
+			limiter.clone(),
+			config.rate_limit.max_retries,
+		);
+		let client = RpcClient::new_sender(
+			sender,
+			RpcClientConfig::with_commitment(CommitmentConfig::confirmed()),
+		);
+
+		Self {
+			config,
+			client: Arc::new(client),
+			limiter,
+			weight,
+			stats: Mutex::new(EndpointStats::default()),
+		}
+	}
+}
+
+/// Snapshot of an endpoint's health and request counters
+#[derive(Debug)]
+pub struct EndpointMetrics {
+	pub url: String,
+	pub weight: u32,
+	pub health: f64,
+	pub latency_ms: Option<f64>,
+	pub error_rate: f64,
+	pub cooling_down: bool,
+	pub requests: u64,
+	pub failures: u64,
+	pub rate_limited: u64,
+	pub timeouts: u64,
+}
+
+/// RPC endpoints shared by every RPC consumer, built once so that each endpoint's rate limit
+/// holds across all of them. Each request goes to an endpoint picked at random
+/// weighted by its configured weight and its health, which tracks recent latency and errors.
+/// Endpoints in cooldown are only picked when every other endpoint is cooling down as well.
+pub struct EndpointPool {
+	endpoints: Vec<Endpoint>,
+	config: FailoverConfig,
+}
+
+impl EndpointPool {
+	pub fn new(config: &ClientConfig) -> Self {
+		let endpoints = config
+			.get_endpoints()
+			.into_iter()
+			.map(|(config, weight)| Endpoint::new(config, weight))
+			.collect();
+
+		Self {
+			endpoints,
+			config: config.failover.clone(),
+		}
+	}
+
+	pub fn len(&self) -> usize {
+		self.endpoints.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.endpoints.is_empty()
+	}
+
+	pub fn get(&self, index: usize) -> &Endpoint {
+		&self.endpoints[index]
+	}
+
+	/// Picks an endpoint other than the `excluded` ones, returns `None` once every endpoint has
+	/// been excluded
+	pub fn select(&self, excluded: &[usize]) -> Option<usize> {
+		let now = Instant::now();
+		let candidates: Vec<(usize, f64, Option<Instant>)> = self
+			.endpoints
+			.iter()
+			.enumerate()
+			.filter(|(index, _)| !excluded.contains(index))
+			.map(|(index, endpoint)| {
+				let stats = endpoint.stats.lock().unwrap();
+				let cooldown = stats.cooldown_until.filter(|_| stats.cooling_down(now));
+				(index, endpoint.weight as f64 * stats.health(), cooldown)
+			})
+			.collect();
+
+		let available: Vec<(usize, f64)> = candidates
+			.iter()
+			.filter(|(_, score, cooldown)| cooldown.is_none() && *score > 0.0)
+			.map(|(index, score, _)| (*index, *score))
+			.collect();
+
+		if available.is_empty() {
+			// Every remaining endpoint is cooling down, the one that recovers first is tried
+			return candidates
+				.iter()
+				.min_by_key(|(_, _, cooldown)| *cooldown)
+				.map(|(index, _, _)| *index);
+		}
+
+		let total: f64 = available.iter().map(|(_, score)| score).sum();
+		let mut target = rand::thread_rng().gen_range(0.0..total);
+		for (index, score) in &available {
+			if target < *score {
+				return Some(*index);
+			}
+			target -= score;
+		}
+
+		available.last().map(|(index, _)| *index)
+	}
+
+	/// Sends `request` to the endpoints picked by `select` until one succeeds, each endpoint is
+	/// tried at most once. Errors returned by the RPC node itself are not retried.
+	pub async fn request<F, Fut, T>(&self, request: F) -> Result<T>
+	where
+		F: Fn(&Endpoint) -> Fut,
+		Fut: Future<Output = Result<T>>,
+	{
+		let mut tried = Vec::with_capacity(self.len());
+		let mut last_error = AppError::Unknown("No rpc endpoint available".to_string());
+
+		while let Some(index) = self.select(&tried) {
+			tried.push(index);
+			let endpoint = self.get(index);
+
+			let start = Instant::now();
+			// Not awaited in place so that `request` is not borrowed across the await and only
+			// needs to be `Send`
+			let response = request(endpoint);
+			match response.await {
+				Ok(output) => {
+					self.record_success(index, start.elapsed());
+					return Ok(output);
+				}
+				Err(e) => match FailureKind::from_error(&e) {
+					Some(kind) => {
+						self.record_failure(index, kind);
+						log::warn!(
+							"[RPC] {} failed ({:?}): {}, failing over",
+							endpoint.config.url,
+							kind,
+							e
+						);
+						last_error = e;
+					}
+					None => return Err(e),
+				},
+			}
+		}
+
+		Err(last_error)
+	}
+
+	/// `request` for a single call of the endpoint's client
+	pub async fn with_client<F, Fut, T>(&self, request: F) -> Result<T>
+	where
+		F: Fn(Arc<RpcClient>) -> Fut,
+		Fut: Future<Output = ClientResult<T>>,
+	{
+		self.request(|endpoint| {
+			let response = request(endpoint.client.clone());
+			async move { response.await.map_err(classify_error) }
+		})
+		.await
+	}
+
+	pub fn record_success(&self, index: usize, latency: Duration) {
+		let mut stats = self.endpoints[index].stats.lock().unwrap();
+		let latency_ms = latency.as_secs_f64() * 1000.0;
+		stats.requests += 1;
+		stats.latency_ms = Some(match stats.latency_ms {
+			Some(average) => average + EWMA_ALPHA * (latency_ms - average),
+			None => latency_ms,
+		});
+		stats.error_rate -= EWMA_ALPHA * stats.error_rate;
+		stats.consecutive_failures = 0;
+		stats.cooldown_until = None;
+	}
+
+	pub fn record_failure(&self, index: usize, kind: FailureKind) {
+		let mut stats = self.endpoints[index].stats.lock().unwrap();
+		stats.requests += 1;
+		stats.failures += 1;
+		stats.error_rate += EWMA_ALPHA * (1.0 - stats.error_rate);
+		stats.consecutive_failures += 1;
+
+		match kind {
+			FailureKind::RateLimited => stats.rate_limited += 1,
+			FailureKind::Timeout => stats.timeouts += 1,
+			FailureKind::Error => {}
+		}
+
+		if kind != FailureKind::Error
+			|| stats.consecutive_failures >= self.config.max_consecutive_failures
+		{
+			stats.cooldown_until =
+				Some(Instant::now() + Duration::from_secs(self.config.cooldown_secs));
+		}
+	}
+
+	pub fn metrics(&self) -> Vec<EndpointMetrics> {
+		let now = Instant::now();
+		self.endpoints
+			.iter()
+			.map(|endpoint| {
+				let stats = endpoint.stats.lock().unwrap();
+				EndpointMetrics {
+					url: endpoint.config.url.clone(),
+					weight: endpoint.weight,
+					health: stats.health(),
+					latency_ms: stats.latency_ms,
+					error_rate: stats.error_rate,
+					cooling_down: stats.cooling_down(now),
+					requests: stats.requests,
+					failures: stats.failures,
+					rate_limited: stats.rate_limited,
+					timeouts: stats.timeouts,
+				}
+			})
+			.collect()
+	}
+
+	/// Logs the metrics of every endpoint every `metrics_interval_secs` until ctrl+c is received
+	pub async fn report_metrics(self: Arc<Self>) {
+		if self.config.metrics_interval_secs == 0 {
+			return;
+		}
+		let mut ticker = interval(Duration::from_secs(self.config.metrics_interval_secs));
+		ticker.tick().await;
+
+		loop {
+			tokio::select! {
+				_ = ticker.tick() => self.log_metrics(),
+				_ = tokio::signal::ctrl_c() => break,
+			}
+		}
+	}
+
+	/// Logs the metrics of every endpoint, api keys are left out
+	pub fn log_metrics(&self) {
+		for metrics in self.metrics() {
+			log::info!(
+				"[RPC] {} weight: {} health: {:.2} latency: {:.0}ms errors: {:.2} cooldown: {} \
+				requests: {} failures: {} rate limited: {} timeouts: {}",
+				metrics.url,
+				metrics.weight,
+				metrics.health,
+				metrics.latency_ms.unwrap_or(0.0),
+				metrics.error_rate,
+				metrics.cooling_down,
+				metrics.requests,
+				metrics.failures,
+				metrics.rate_limited,
+				metrics.timeouts
+			);
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::config::{
+		EndpointConfig, IngestionMode, RateLimitConfig, RequeueConfig, RetryConfig, WsConfig,
+	};
+
+	fn pool() -> EndpointPool {
+		EndpointPool::new(&ClientConfig {
+			url: "primary".to_string(),
+			api_key: String::new(),
+			worker_threads: 1,
+			ingestion: IngestionMode::Slots,
+			endpoints: vec![EndpointConfig {
+				url: "secondary".to_string(),
+				api_key: String::new(),
+				weight: 1,
+			}],
+			failover: FailoverConfig::default(),
+			rate_limit: RateLimitConfig::default(),
+			retry: RetryConfig::default(),
+			requeue: RequeueConfig::default(),
+			ws: WsConfig::default(),
+		})
+	}
+
+	#[test]
+	fn test_rate_limited_endpoint_is_skipped() {
+		let pool = pool();
+
+		pool.record_failure(0, FailureKind::RateLimited);
+
+		for _ in 0..20 {
+			assert_eq!(pool.select(&[]), Some(1));
+		}
+		// Cooling down endpoints are still tried once nothing else is left
+		assert_eq!(pool.select(&[1]), Some(0));
+		assert_eq!(pool.select(&[0, 1]), None);
+	}
+
+	#[test]
+	fn test_errors_lower_health_until_cooldown() {
+		let pool = pool();
+
+		pool.record_success(1, Duration::from_millis(100));
+		pool.record_failure(1, FailureKind::Error);
+		let metrics = &pool.metrics()[1];
+		assert!(metrics.health < 1.0);
+		assert!(!metrics.cooling_down);
+
+		pool.record_failure(1, FailureKind::Error);
+		pool.record_failure(1, FailureKind::Error);
+		assert!(pool.metrics()[1].cooling_down);
+
+		pool.record_success(1, Duration::from_millis(100));
+		assert!(!pool.metrics()[1].cooling_down);
+		assert_eq!(pool.metrics()[1].failures, 3);
+	}
+}
@@ -1,30 +1,29 @@
+use super::endpoints::Endpoint;
+use super::retry::{classify_block_error, RetryPolicy};
 use super::Gettable;
-use crate::config::ClientConfig;
-use crate::error::{AppError, Result};
-use solana_client::{
-    nonblocking::rpc_client::RpcClient, rpc_config::RpcBlockConfig, rpc_response::SlotInfo,
-};
+use crate::error::Result;
+use solana_client::{rpc_config::RpcBlockConfig, rpc_response::SlotInfo};
 use solana_sdk::commitment_config::CommitmentConfig;
 use solana_transaction_status::{TransactionDetails, UiConfirmedBlock};
 use std::future::Future;
 use std::pin::Pin;
-use tokio::time::{timeout, sleep, Duration};
+use tokio::time::sleep;
 
-/// Retrieve block from a slot number and outputs the a tuple of `SlotInfo` and `UiConfirmedBlock`
+/// Retrieve block from a slot number and outputs the a tuple of `SlotInfo` and `UiConfirmedBlock`.
+/// Skipped slots fail with `AppError::SlotSkipped` and blocks that are not available yet with
+/// `AppError::BlockNotAvailable`, which the rpc workers re-queue according to
+/// `[client.requeue]`. Transient errors are retried according to `[client.retry]`, each HTTP
+/// request times out on its own so that waits for the endpoint's rate limit do not count.
 impl Gettable for SlotInfo {
     type Output = (Self, UiConfirmedBlock);
     fn get(
         input: SlotInfo,
-        config: &ClientConfig,
+        endpoint: &Endpoint,
     ) -> Pin<Box<dyn Future<Output = Result<Self::Output>> + Send + 'static>> {
-        let url = config.get_url();
+        let client = endpoint.client.clone();
+        let retry_policy = RetryPolicy::new(&endpoint.config.retry);
         Box::pin(async move {
-            let client = RpcClient::new_with_commitment(url, CommitmentConfig::confirmed());
-
-            // Block for current slot is typically not yet processed, the slot before it (parent)
-            // can or cannot be processed testing showed that 2 slots behind is generally the
-            // most recent processed block for RPC call
-            let slot = input.slot - 2;
+            let slot = input.slot;
 
             let block_config = RpcBlockConfig {
                 encoding: None,
@@ -34,45 +33,44 @@ impl Gettable for SlotInfo {
                 transaction_details: Some(TransactionDetails::Full),
             };
 
-            let max_retries = 3;
-            let retry_delay = Duration::from_millis(500);
-            let total_timeout = Duration::from_secs(10);
-
-            let retry_future = async {
-                let mut retries = 0;
-                loop {
-                    match client
-                        .get_block_with_config(slot, block_config.clone())
-                        .await
-                    {
-                        Ok(block) => return Ok((input, block)),
-                        Err(e) if retries < max_retries => {
-                            retries += 1;
-                            log::warn!(
-                                "Error getting block (attempt {}): {}. Retrying...",
-                                retries, e
-                            );
-                            sleep(retry_delay).await;
-                        }
-                        Err(e) => return Err(AppError::from(e)),
+            let mut retries = 0;
+            loop {
+                match client
+                    .get_block_with_config(slot, block_config)
+                    .await
+                    .map_err(|e| classify_block_error(e, slot))
+                {
+                    Ok(block) => return Ok((input, block)),
+                    Err(e) if retry_policy.should_retry(&e, retries) => {
+                        retries += 1;
+                        let delay = retry_policy.delay(retries);
+                        log::warn!(
+                            "Error getting block (attempt {}): {}. Retrying in {:?}...",
+                            retries,
+                            e,
+                            delay
+                        );
+                        sleep(delay).await;
                     }
+                    Err(e) => return Err(e),
                 }
-            };
-
-            match timeout(total_timeout, retry_future).await {
-                Ok(result) => result,
-                Err(_) => Err(AppError::TimeoutError),
             }
         })
     }
+
+    fn gap_slot(&self) -> Option<u64> {
+        Some(self.slot)
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::client::endpoints::EndpointPool;
     use crate::config::load_config;
 
     #[tokio::test]
+    #[ignore = "requires the rpc endpoint in Config.toml"]
     async fn test_get_block() {
         let config = load_config("Config.toml").unwrap();
         let url = config.client.get_url();
@@ -88,9 +86,10 @@ mod test {
             root: 12312,
         };
 
-        let (slot_result, block_result) = SlotInfo::get(slot_info, &config.client).await.unwrap();
+        let endpoints = EndpointPool::new(&config.client);
+        let (slot_result, block_result) = SlotInfo::get(slot_info, endpoints.get(0)).await.unwrap();
 
         assert!(slot_result.slot > 0);
-        assert!(block_result.blockhash != "".to_string());
+        assert!(!block_result.blockhash.is_empty());
     }
 }
@@ -0,0 +1,87 @@
+use super::endpoints::Endpoint;
+use super::retry::{classify_error, RetryPolicy};
+use super::Gettable;
+use crate::error::Result;
+use solana_client::{rpc_config::RpcTransactionConfig, rpc_response::SlotInfo};
+use solana_sdk::{commitment_config::CommitmentConfig, signature::Signature};
+use solana_transaction_status::{UiConfirmedBlock, UiTransactionEncoding};
+use std::future::Future;
+use std::pin::Pin;
+use std::str::FromStr;
+use tokio::time::sleep;
+
+/// Signature of a transaction reported by the logs watcher and the commitment it was seen at
+#[derive(Clone, Debug)]
+pub struct WatchedSignature {
+	pub signature: String,
+	pub commitment: CommitmentConfig,
+}
+
+/// Retrieves a watched transaction with `getTransaction`. Outputs a block holding only the watched
+/// transaction so that it goes through the same processing and storage as full blocks. The slot
+/// and block time come from the transaction, the block header is not fetched so the blockhash is
+/// left empty and the block is stored without a header.
+impl Gettable for WatchedSignature {
+	type Output = (SlotInfo, UiConfirmedBlock);
+	fn get(
+		input: WatchedSignature,
+		endpoint: &Endpoint,
+	) -> Pin<Box<dyn Future<Output = Result<Self::Output>> + Send + 'static>> {
+		let client = endpoint.client.clone();
+		let retry_policy = RetryPolicy::new(&endpoint.config.retry);
+		Box::pin(async move {
+			let signature = Signature::from_str(&input.signature)?;
+
+			let transaction_config = RpcTransactionConfig {
+				encoding: Some(UiTransactionEncoding::Json),
+				commitment: Some(input.commitment),
+				max_supported_transaction_version: Some(0),
+			};
+
+			// The transaction may not be served yet right after its logs notification
+			let mut retries = 0;
+			let transaction = loop {
+				match client
+					.get_transaction_with_config(&signature, transaction_config)
+					.await
+					.map_err(classify_error)
+				{
+					Ok(result) => break result,
+					Err(e) if retry_policy.should_retry(&e, retries) => {
+						retries += 1;
+						let delay = retry_policy.delay(retries);
+						log::warn!(
+							"Error getting transaction {} (attempt {}): {}. Retrying in {:?}...",
+							input.signature,
+							retries,
+							e,
+							delay
+						);
+						sleep(delay).await;
+					}
+					Err(e) => return Err(e),
+				}
+			};
+
+			// The parent is unknown without the block, processing only uses the slot
+			let slot_info = SlotInfo {
+				slot: transaction.slot,
+				parent: 0,
+				root: 0,
+			};
+			let block = UiConfirmedBlock {
+				previous_blockhash: String::new(),
+				blockhash: String::new(),
+				parent_slot: 0,
+				transactions: Some(vec![transaction.transaction]),
+				signatures: None,
+				rewards: None,
+				num_reward_partitions: None,
+				block_time: transaction.block_time,
+				block_height: None,
+			};
+
+			Ok((slot_info, block))
+		})
+	}
+}
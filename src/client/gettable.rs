@@ -1,10 +1,18 @@
-use crate::config::ClientConfig;
+use super::endpoints::Endpoint;
 use crate::error::Result;
 use std::future::Future;
 use std::pin::Pin;
 
-/// Enables rpc workers to be generic over this trait
-pub trait Gettable: Send + 'static {
+/// Enables rpc workers to be generic over this trait, inputs are cloned so that a failed request
+/// can be retried against another endpoint. Requests are sent with the endpoint's long lived
+/// client, owned by the `RpcWorkerManager`, which also applies the endpoint's rate limit
+pub trait Gettable: Clone + Send + 'static {
 	type Output;
-	fn get(input: Self, config: &ClientConfig) -> Pin<Box<dyn Future<Output = Result<Self::Output>> + 'static + Send>>;
+	fn get(input: Self, endpoint: &Endpoint) -> Pin<Box<dyn Future<Output = Result<Self::Output>> + 'static + Send>>;
+
+	/// Slot recorded as missing in `slot_gaps` when the rpc workers give up on the input, so
+	/// that the backfill task retrieves it later
+	fn gap_slot(&self) -> Option<u64> {
+		None
+	}
 }
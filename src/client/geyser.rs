@@ -0,0 +1,481 @@
+use super::retry::RetryPolicy;
+use crate::{
+	channel::StageSender,
+	config::{GeyserConfig, RetryConfig},
+	error::{AppError, Result},
+};
+use futures::StreamExt;
+use hyper_util::rt::TokioIo;
+use log::{error, info, warn};
+use solana_client::rpc_response::SlotInfo;
+use solana_sdk::reward_type::RewardType;
+use solana_transaction_status::{
+	EncodedTransactionWithStatusMeta, Reward, UiConfirmedBlock, UiTransactionEncoding,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
+use tokio_rustls::{
+	rustls::{ClientConfig, OwnedTrustAnchor, RootCertStore, ServerName},
+	TlsConnector,
+};
+use tonic::{
+	metadata::AsciiMetadataValue,
+	service::{interceptor::InterceptedService, Interceptor},
+	transport::Channel,
+	Request,
+};
+use yellowstone_grpc_proto::{
+	convert_from::create_tx_with_meta,
+	prelude::{
+		geyser_client::GeyserClient, subscribe_update::UpdateOneof, CommitmentLevel,
+		Reward as ProtoReward, RewardType as ProtoRewardType, SubscribeRequest,
+		SubscribeRequestFilterBlocks, SubscribeUpdateBlock, SubscribeUpdateTransactionInfo,
+	},
+};
+
+/// Blocks with their transactions are well above tonic's default limit of 4 MiB
+const MAX_DECODING_MESSAGE_SIZE: usize = 256 * 1024 * 1024;
+
+/// Streams confirmed blocks from a Yellowstone (Geyser) gRPC endpoint, used when
+/// `ingestion = "geyser"`. Blocks arrive as soon as the validator confirms them and are sent
+/// straight to the processing workers in the same form the rpc workers produce, ctrl+c handler
+/// implemented for graceful shutdown. Like `WsClient` the stream is supervised, when it fails,
+/// closes or no update arrives for `stall_timeout_secs` it is opened again with exponential
+/// backoff.
+pub struct GeyserSource {
+	pub config: GeyserConfig,
+	pub tx: StageSender<(SlotInfo, UiConfirmedBlock)>,
+}
+
+impl GeyserSource {
+	pub fn new(config: GeyserConfig, tx: StageSender<(SlotInfo, UiConfirmedBlock)>) -> Self {
+		Self { config, tx }
+	}
+
+	/// Subscribes to blocks with their transactions and forwards them until ctrl+c is received,
+	/// subscribing again after a backoff whenever the stream ends. The backoff is reset once a
+	/// stream delivered updates.
+	pub async fn subscribe(&self) -> Result<()> {
+		let backoff = RetryPolicy::new(&RetryConfig {
+			max_retries: 0,
+			base_delay_ms: self.config.reconnect_base_delay_ms,
+			max_delay_ms: self.config.reconnect_max_delay_ms,
+		});
+		let stall_timeout = (self.config.stall_timeout_secs > 0)
+			.then(|| Duration::from_secs(self.config.stall_timeout_secs));
+		let shutdown = tokio::signal::ctrl_c();
+		tokio::pin!(shutdown);
+		let mut attempt = 0;
+
+		loop {
+			let mut updates = 0;
+			tokio::select! {
+				result = self.stream(stall_timeout, &mut updates) => {
+					if let Err(e) = result {
+						error!("[GEYSER] Stream ended: {}", e);
+					}
+				}
+				_ = &mut shutdown => break,
+			}
+
+			if updates > 0 {
+				attempt = 0;
+			}
+			attempt += 1;
+			let delay = backoff.delay(attempt);
+			info!("[GEYSER] Reconnecting in {:?} (attempt {})", delay, attempt);
+
+			tokio::select! {
+				_ = tokio::time::sleep(delay) => {}
+				_ = &mut shutdown => break,
+			}
+		}
+
+		info!("Ctrl+C received, shutting down geyser stream...");
+		Ok(())
+	}
+
+	/// Connects, subscribes and forwards blocks until the stream fails, is closed by the server
+	/// or stalls, counting the updates received in `updates`
+	async fn stream(&self, stall_timeout: Option<Duration>, updates: &mut u64) -> Result<()> {
+		let mut client = self.connect().await?;
+
+		let request = SubscribeRequest {
+			blocks: HashMap::from([(
+				"solder".to_string(),
+				SubscribeRequestFilterBlocks {
+					account_include: Vec::new(),
+					include_transactions: Some(true),
+					include_accounts: Some(false),
+					include_entries: Some(false),
+				},
+			)]),
+			commitment: Some(CommitmentLevel::Confirmed as i32),
+			..Default::default()
+		};
+
+		info!("Subscribing to geyser blocks...");
+		let mut stream = client
+			.subscribe(futures::stream::iter([request]))
+			.await
+			.map_err(|e| AppError::GeyserError(e.to_string()))?
+			.into_inner();
+		info!("Subscribed to geyser blocks");
+
+		loop {
+			let update = match stall_timeout {
+				Some(timeout) => {
+					tokio::time::timeout(timeout, stream.next())
+						.await
+						.map_err(|_| {
+							AppError::GeyserError(format!("No update received for {:?}", timeout))
+						})?
+				}
+				None => stream.next().await,
+			};
+			let update = match update {
+				Some(Ok(update)) => update,
+				Some(Err(status)) => return Err(AppError::GeyserError(status.to_string())),
+				None => return Err(AppError::GeyserError("Stream closed by server".to_string())),
+			};
+			*updates += 1;
+
+			// Pings keep the connection alive and carry no data. The stage sender blocks while
+			// the processing channel is full so it is kept off the runtime's worker threads.
+			if let Some(UpdateOneof::Block(block)) = update.update_oneof {
+				let tx = self.tx.clone();
+				if let Err(e) =
+					tokio::task::spawn_blocking(move || tx.send(block_from_update(block))).await?
+				{
+					error!("Error sending data: {}", e);
+				}
+			}
+		}
+	}
+
+	/// Opens a channel to the endpoint, over TLS for `https` endpoints, with the `x-token`
+	/// header added to every request
+	async fn connect(&self) -> Result<GeyserClient<InterceptedService<Channel, impl Interceptor>>> {
+		let x_token = self
+			.config
+			.x_token
+			.as_deref()
+			.map(AsciiMetadataValue::try_from)
+			.transpose()
+			.map_err(|e| AppError::GeyserError(e.to_string()))?;
+
+		let tls = tls_connector();
+		let connector = tower::service_fn(move |uri: tonic::transport::Uri| {
+			let tls = tls.clone();
+			async move { connect_stream(uri, tls).await.map(TokioIo::new) }
+		});
+		let channel = Channel::from_shared(self.config.endpoint.clone())
+			.map_err(|e| AppError::GeyserError(e.to_string()))?
+			.connect_timeout(Duration::from_secs(10))
+			.connect_with_connector(connector)
+			.await
+			.map_err(|e| AppError::GeyserError(e.to_string()))?;
+
+		// `tonic::Status` is the error type required by `Interceptor`
+		#[allow(clippy::result_large_err)]
+		let interceptor = move |mut request: Request<()>| {
+			if let Some(x_token) = x_token.clone() {
+				request.metadata_mut().insert("x-token", x_token);
+			}
+			Ok(request)
+		};
+
+		Ok(GeyserClient::with_interceptor(channel, interceptor)
+			.max_decoding_message_size(MAX_DECODING_MESSAGE_SIZE))
+	}
+}
+
+/// A connection to a geyser endpoint, plain TCP or TLS
+trait GeyserStream: AsyncRead + AsyncWrite + Send + Unpin {}
+
+impl<T: AsyncRead + AsyncWrite + Send + Unpin> GeyserStream for T {}
+
+/// rustls client negotiating HTTP/2, servers are verified against the Mozilla root certificates
+/// bundled by `webpki-roots` so that no system OpenSSL is needed
+fn tls_connector() -> TlsConnector {
+	let mut roots = RootCertStore::empty();
+	roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|anchor| {
+		OwnedTrustAnchor::from_subject_spki_name_constraints(
+			anchor.subject,
+			anchor.spki,
+			anchor.name_constraints,
+		)
+	}));
+	let mut config = ClientConfig::builder()
+		.with_safe_defaults()
+		.with_root_certificates(roots)
+		.with_no_client_auth();
+	config.alpn_protocols = vec![b"h2".to_vec()];
+
+	TlsConnector::from(Arc::new(config))
+}
+
+/// Opens a TCP connection to the endpoint at `uri`, with a TLS session on top for `https`
+async fn connect_stream(
+	uri: tonic::transport::Uri,
+	tls: TlsConnector,
+) -> std::io::Result<Box<dyn GeyserStream>> {
+	let invalid = |message: &str| std::io::Error::new(std::io::ErrorKind::InvalidInput, message);
+	let host = uri
+		.host()
+		.map(|host| host.trim_matches(|c| c == '[' || c == ']'))
+		.ok_or_else(|| invalid("endpoint has no host"))?;
+	let https = uri.scheme_str() == Some("https");
+	let port = uri.port_u16().unwrap_or(if https { 443 } else { 80 });
+
+	let tcp = TcpStream::connect((host, port)).await?;
+	tcp.set_nodelay(true)?;
+	if !https {
+		return Ok(Box::new(tcp));
+	}
+
+	let server_name = ServerName::try_from(host).map_err(|_| invalid("invalid endpoint host"))?;
+	Ok(Box::new(tls.connect(server_name, tcp).await?))
+}
+
+/// Converts a geyser block into the `SlotInfo` and `UiConfirmedBlock` returned by `getBlock`,
+/// transactions that cannot be converted are skipped
+pub fn block_from_update(block: SubscribeUpdateBlock) -> (SlotInfo, UiConfirmedBlock) {
+	let transactions: Vec<EncodedTransactionWithStatusMeta> = block
+		.transactions
+		.into_iter()
+		.filter_map(|tx| encode_transaction(block.slot, tx))
+		.collect();
+
+	let (rewards, num_reward_partitions) = match block.rewards {
+		Some(rewards) => (
+			rewards.rewards.into_iter().map(to_reward).collect(),
+			rewards
+				.num_partitions
+				.map(|partitions| partitions.num_partitions),
+		),
+		None => (Vec::new(), None),
+	};
+
+	let slot_info = SlotInfo {
+		slot: block.slot,
+		parent: block.parent_slot,
+		root: 0,
+	};
+	let ui_block = UiConfirmedBlock {
+		previous_blockhash: block.parent_blockhash,
+		blockhash: block.blockhash,
+		parent_slot: block.parent_slot,
+		transactions: Some(transactions),
+		signatures: None,
+		rewards: Some(rewards),
+		num_reward_partitions,
+		block_time: block.block_time.map(|time| time.timestamp),
+		block_height: block.block_height.map(|height| height.block_height),
+	};
+
+	(slot_info, ui_block)
+}
+
+/// Json encoded like the transactions of `getBlock` so that accounts are read the same way
+fn encode_transaction(
+	slot: u64,
+	tx: SubscribeUpdateTransactionInfo,
+) -> Option<EncodedTransactionWithStatusMeta> {
+	let encoded = create_tx_with_meta(tx)
+		.map_err(|e| e.to_string())
+		.and_then(|tx| {
+			tx.encode(UiTransactionEncoding::Json, Some(0), true)
+				.map_err(|e| e.to_string())
+		});
+
+	match encoded {
+		Ok(tx) => Some(tx),
+		Err(e) => {
+			warn!("Skipping transaction in slot {}: {}", slot, e);
+			None
+		}
+	}
+}
+
+fn to_reward(reward: ProtoReward) -> Reward {
+	let reward_type = match ProtoRewardType::try_from(reward.reward_type) {
+		Ok(ProtoRewardType::Fee) => Some(RewardType::Fee),
+		Ok(ProtoRewardType::Rent) => Some(RewardType::Rent),
+		Ok(ProtoRewardType::Staking) => Some(RewardType::Staking),
+		Ok(ProtoRewardType::Voting) => Some(RewardType::Voting),
+		_ => None,
+	};
+
+	Reward {
+		pubkey: reward.pubkey,
+		lamports: reward.lamports,
+		post_balance: reward.post_balance,
+		reward_type,
+		commission: reward.commission.parse().ok(),
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::{
+		channel::stage_channel,
+		config::{OverflowPolicy, StageConfig},
+	};
+	use futures::Stream;
+	use std::pin::Pin;
+	use tokio_stream::wrappers::TcpListenerStream;
+	use tonic::{transport::Server, Request, Response, Status, Streaming};
+	use yellowstone_grpc_proto::prelude::{
+		geyser_server::{Geyser, GeyserServer},
+		BlockHeight, GetBlockHeightRequest, GetBlockHeightResponse, GetLatestBlockhashRequest,
+		GetLatestBlockhashResponse, GetSlotRequest, GetSlotResponse, GetVersionRequest,
+		GetVersionResponse, IsBlockhashValidRequest, IsBlockhashValidResponse, PingRequest,
+		PongResponse, Rewards, SubscribeUpdate, UnixTimestamp,
+	};
+
+	const BLOCKHASH: &str = "4sGjMW1sUnHzSxGspuhpqLDx6wiyjNtZAMdL4VZHirAn";
+	const PARENT_BLOCKHASH: &str = "8HrqKBA2Dj5YLqbPPtgSnjTLwp3XM7V4wZtFsDNqCDxh";
+	const LEADER: &str = "9QU2QSxhb24FUX3Tu2FpczXjpK3VYrvRudywSZaM29mF";
+
+	/// Geyser server that streams a single block to every subscriber
+	struct MockGeyser;
+
+	#[tonic::async_trait]
+	impl Geyser for MockGeyser {
+		type SubscribeStream =
+			Pin<Box<dyn Stream<Item = std::result::Result<SubscribeUpdate, Status>> + Send>>;
+
+		async fn subscribe(
+			&self,
+			_request: Request<Streaming<SubscribeRequest>>,
+		) -> std::result::Result<Response<Self::SubscribeStream>, Status> {
+			let block = SubscribeUpdateBlock {
+				slot: 100,
+				blockhash: BLOCKHASH.to_string(),
+				parent_slot: 99,
+				parent_blockhash: PARENT_BLOCKHASH.to_string(),
+				block_time: Some(UnixTimestamp {
+					timestamp: 1_700_000_000,
+				}),
+				block_height: Some(BlockHeight { block_height: 90 }),
+				rewards: Some(Rewards {
+					rewards: vec![ProtoReward {
+						pubkey: LEADER.to_string(),
+						lamports: 5_000,
+						post_balance: 1_000_000,
+						reward_type: ProtoRewardType::Fee as i32,
+						commission: String::new(),
+					}],
+					num_partitions: None,
+				}),
+				..Default::default()
+			};
+			let update = SubscribeUpdate {
+				filters: vec!["solder".to_string()],
+				update_oneof: Some(UpdateOneof::Block(block)),
+			};
+
+			Ok(Response::new(Box::pin(futures::stream::iter([Ok(update)]))))
+		}
+
+		async fn ping(
+			&self,
+			_request: Request<PingRequest>,
+		) -> std::result::Result<Response<PongResponse>, Status> {
+			Err(Status::unimplemented("ping"))
+		}
+
+		async fn get_latest_blockhash(
+			&self,
+			_request: Request<GetLatestBlockhashRequest>,
+		) -> std::result::Result<Response<GetLatestBlockhashResponse>, Status> {
+			Err(Status::unimplemented("get_latest_blockhash"))
+		}
+
+		async fn get_block_height(
+			&self,
+			_request: Request<GetBlockHeightRequest>,
+		) -> std::result::Result<Response<GetBlockHeightResponse>, Status> {
+			Err(Status::unimplemented("get_block_height"))
+		}
+
+		async fn get_slot(
+			&self,
+			_request: Request<GetSlotRequest>,
+		) -> std::result::Result<Response<GetSlotResponse>, Status> {
+			Err(Status::unimplemented("get_slot"))
+		}
+
+		async fn is_blockhash_valid(
+			&self,
+			_request: Request<IsBlockhashValidRequest>,
+		) -> std::result::Result<Response<IsBlockhashValidResponse>, Status> {
+			Err(Status::unimplemented("is_blockhash_valid"))
+		}
+
+		async fn get_version(
+			&self,
+			_request: Request<GetVersionRequest>,
+		) -> std::result::Result<Response<GetVersionResponse>, Status> {
+			Err(Status::unimplemented("get_version"))
+		}
+	}
+
+	#[tokio::test]
+	async fn test_subscribe_to_mock_server() {
+		let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let addr = listener.local_addr().unwrap();
+		tokio::spawn(
+			Server::builder()
+				.add_service(GeyserServer::new(MockGeyser))
+				.serve_with_incoming(TcpListenerStream::new(listener)),
+		);
+
+		let (tx, rx) = stage_channel(
+			"PROCESSING",
+			&StageConfig {
+				capacity: 8,
+				overflow: OverflowPolicy::Block,
+			},
+		);
+		let source = GeyserSource::new(
+			GeyserConfig {
+				endpoint: format!("http://{}", addr),
+				reconnect_base_delay_ms: 10,
+				reconnect_max_delay_ms: 100,
+				..Default::default()
+			},
+			tx,
+		);
+		let subscription = tokio::spawn(async move { source.subscribe().await });
+
+		// The mock closes the stream after its only block, the block is streamed again once
+		// the source reconnects
+		let (first, second) = tokio::task::spawn_blocking(move || {
+			let timeout = Duration::from_secs(10);
+			(rx.recv_timeout(timeout), rx.recv_timeout(timeout))
+		})
+		.await
+		.unwrap();
+		subscription.abort();
+		assert_eq!(second.unwrap().0.slot, 100);
+
+		let (slot_info, block) = first.unwrap();
+		assert_eq!(slot_info.slot, 100);
+		assert_eq!(slot_info.parent, 99);
+		assert_eq!(block.blockhash, BLOCKHASH);
+		assert_eq!(block.previous_blockhash, PARENT_BLOCKHASH);
+		assert_eq!(block.block_time, Some(1_700_000_000));
+		assert_eq!(block.block_height, Some(90));
+		assert_eq!(block.transactions.unwrap().len(), 0);
+
+		let rewards = block.rewards.unwrap();
+		assert_eq!(rewards[0].pubkey, LEADER);
+		assert_eq!(rewards[0].reward_type, Some(RewardType::Fee));
+	}
+}
@@ -0,0 +1,115 @@
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Upper bounds of the latency buckets in milliseconds, slower requests fall in a last bucket
+const BUCKET_BOUNDS_MS: [u64; 8] = [50, 100, 200, 400, 800, 1600, 3200, 6400];
+
+/// Histogram of the time taken to retrieve a block, shared by the workers of a manager. Counts
+/// are taken and reset by each report so that every report covers the last interval.
+#[derive(Debug, Default)]
+pub struct LatencyHistogram {
+	buckets: [AtomicU64; BUCKET_BOUNDS_MS.len() + 1],
+}
+
+/// Latency percentiles of an interval, each is the upper bound of the bucket it falls in and
+/// `None` when it is slower than the last bound
+#[derive(Debug, PartialEq, Eq)]
+pub struct LatencySummary {
+	pub count: u64,
+	pub p50_ms: Option<u64>,
+	pub p90_ms: Option<u64>,
+	pub p99_ms: Option<u64>,
+}
+
+impl LatencyHistogram {
+	pub fn record(&self, latency: Duration) {
+		let latency_ms = latency.as_millis() as u64;
+		let bucket = BUCKET_BOUNDS_MS
+			.iter()
+			.position(|bound| latency_ms <= *bound)
+			.unwrap_or(BUCKET_BOUNDS_MS.len());
+		self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+	}
+
+	/// Summarises the latencies recorded since the last call, `None` if there were none
+	pub fn take_summary(&self) -> Option<LatencySummary> {
+		let counts: Vec<u64> = self
+			.buckets
+			.iter()
+			.map(|bucket| bucket.swap(0, Ordering::Relaxed))
+			.collect();
+		let count: u64 = counts.iter().sum();
+		if count == 0 {
+			return None;
+		}
+
+		let percentile = |quantile: f64| {
+			let rank = (count as f64 * quantile).ceil() as u64;
+			let mut seen = 0;
+			let bucket = counts
+				.iter()
+				.position(|bucket_count| {
+					seen += bucket_count;
+					seen >= rank
+				})
+				.unwrap_or(BUCKET_BOUNDS_MS.len());
+			BUCKET_BOUNDS_MS.get(bucket).copied()
+		};
+
+		Some(LatencySummary {
+			count,
+			p50_ms: percentile(0.5),
+			p90_ms: percentile(0.9),
+			p99_ms: percentile(0.99),
+		})
+	}
+}
+
+impl fmt::Display for LatencySummary {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let bound = |percentile: Option<u64>| match percentile {
+			Some(bound) => format!("<={}ms", bound),
+			None => format!(">{}ms", BUCKET_BOUNDS_MS[BUCKET_BOUNDS_MS.len() - 1]),
+		};
+		write!(
+			f,
+			"{} blocks p50: {} p90: {} p99: {}",
+			self.count,
+			bound(self.p50_ms),
+			bound(self.p90_ms),
+			bound(self.p99_ms)
+		)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_latency_summary() {
+		let histogram = LatencyHistogram::default();
+		assert_eq!(histogram.take_summary(), None);
+
+		for _ in 0..90 {
+			histogram.record(Duration::from_millis(150));
+		}
+		for _ in 0..9 {
+			histogram.record(Duration::from_millis(700));
+		}
+		histogram.record(Duration::from_secs(10));
+
+		assert_eq!(
+			histogram.take_summary(),
+			Some(LatencySummary {
+				count: 100,
+				p50_ms: Some(200),
+				p90_ms: Some(200),
+				p99_ms: Some(800),
+			})
+		);
+		// Counts are reset once taken
+		assert_eq!(histogram.take_summary(), None);
+	}
+}
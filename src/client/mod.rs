@@ -1,11 +1,19 @@
+#[cfg(feature = "geyser")]
+pub mod geyser;
+pub mod endpoints;
 pub mod gettable;
+pub mod latency;
+pub mod rate_limiter;
+pub mod retry;
+pub mod rpc_sender;
 pub mod rpc_worker;
 pub mod subscribable;
 pub mod subscribe_blocks;
 pub mod subscribe_logs;
 pub mod subscribe_slot;
 pub mod get_block_from_slot;
+pub mod get_transaction;
 pub mod ws;
 
 pub use gettable::Gettable;
-pub use subscribable::Subscribable;
+pub use subscribable::{IntoStageItem, Subscribable};
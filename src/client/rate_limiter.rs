@@ -0,0 +1,117 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+
+/// Token bucket limiting the requests sent to an RPC endpoint, shared by every rpc worker. The
+/// bucket refills at `requests_per_sec` up to `burst` tokens, each request takes one. A rate
+/// limited response pauses the bucket for the `Retry-After` the endpoint asked for.
+pub struct RateLimiter {
+	requests_per_sec: f64,
+	burst: f64,
+	bucket: Mutex<Bucket>,
+}
+
+struct Bucket {
+	tokens: f64,
+	last_refill: Instant,
+	paused_until: Option<Instant>,
+}
+
+impl RateLimiter {
+	/// A `requests_per_sec` of zero only applies pauses
+	pub fn new(requests_per_sec: f64, burst: u32) -> Self {
+		let burst = burst.max(1) as f64;
+		Self {
+			requests_per_sec,
+			burst,
+			bucket: Mutex::new(Bucket {
+				tokens: burst,
+				last_refill: Instant::now(),
+				paused_until: None,
+			}),
+		}
+	}
+
+	/// Waits until a request can be sent
+	pub async fn acquire(&self) {
+		while let Some(wait) = self.try_acquire(Instant::now()) {
+			sleep(wait).await;
+		}
+	}
+
+	/// Takes a token if one is available, otherwise returns how long to wait for the next one
+	fn try_acquire(&self, now: Instant) -> Option<Duration> {
+		let mut bucket = self.bucket.lock().unwrap();
+
+		if let Some(until) = bucket.paused_until {
+			if until > now {
+				return Some(until - now);
+			}
+			bucket.paused_until = None;
+		}
+
+		if self.requests_per_sec <= 0.0 {
+			return None;
+		}
+
+		let elapsed = now
+			.saturating_duration_since(bucket.last_refill)
+			.as_secs_f64();
+		bucket.tokens = (bucket.tokens + elapsed * self.requests_per_sec).min(self.burst);
+		bucket.last_refill = now;
+
+		if bucket.tokens >= 1.0 {
+			bucket.tokens -= 1.0;
+			None
+		} else {
+			Some(Duration::from_secs_f64(
+				(1.0 - bucket.tokens) / self.requests_per_sec,
+			))
+		}
+	}
+
+	/// Holds back every request until `duration` has passed
+	pub fn pause(&self, duration: Duration) {
+		let until = Instant::now() + duration;
+		let mut bucket = self.bucket.lock().unwrap();
+		if bucket
+			.paused_until
+			.is_none_or(|paused_until| paused_until < until)
+		{
+			bucket.paused_until = Some(until);
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_bucket_refills_at_rate() {
+		let limiter = RateLimiter::new(10.0, 2);
+		let now = Instant::now();
+
+		assert_eq!(limiter.try_acquire(now), None);
+		assert_eq!(limiter.try_acquire(now), None);
+		let wait = limiter.try_acquire(now).unwrap();
+		assert!(wait <= Duration::from_millis(100));
+
+		assert_eq!(limiter.try_acquire(now + Duration::from_millis(100)), None);
+	}
+
+	#[test]
+	fn test_pause_holds_back_requests() {
+		let limiter = RateLimiter::new(0.0, 1);
+		assert_eq!(limiter.try_acquire(Instant::now()), None);
+
+		limiter.pause(Duration::from_secs(2));
+		let wait = limiter.try_acquire(Instant::now()).unwrap();
+		assert!(wait > Duration::from_secs(1));
+
+		assert_eq!(
+			limiter.try_acquire(Instant::now() + Duration::from_secs(3)),
+			None
+		);
+	}
+}
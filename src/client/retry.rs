@@ -0,0 +1,120 @@
+use crate::{config::RetryConfig, error::AppError};
+use rand::Rng;
+use solana_client::{
+	client_error::{ClientError, ClientErrorKind},
+	rpc_custom_error::{
+		JSON_RPC_SERVER_ERROR_BLOCK_NOT_AVAILABLE,
+		JSON_RPC_SERVER_ERROR_BLOCK_STATUS_NOT_AVAILABLE_YET,
+		JSON_RPC_SERVER_ERROR_LONG_TERM_STORAGE_SLOT_SKIPPED, JSON_RPC_SERVER_ERROR_SLOT_SKIPPED,
+	},
+	rpc_request::RpcError,
+};
+use std::time::Duration;
+
+/// Maps a client error to a rate limit or the client error itself
+pub fn classify_error(error: ClientError) -> AppError {
+	match error.kind() {
+		ClientErrorKind::Reqwest(e) if e.status().map(|status| status.as_u16()) == Some(429) => {
+			AppError::RateLimited(e.to_string())
+		}
+		_ => AppError::SolanaClientError(Box::new(error)),
+	}
+}
+
+/// Like `classify_error` but also maps the error codes returned for slots without a block and
+/// for blocks the node does not have yet
+pub fn classify_block_error(error: ClientError, slot: u64) -> AppError {
+	if let ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. }) = error.kind() {
+		match *code {
+			JSON_RPC_SERVER_ERROR_SLOT_SKIPPED
+			| JSON_RPC_SERVER_ERROR_LONG_TERM_STORAGE_SLOT_SKIPPED => return AppError::SlotSkipped(slot),
+			JSON_RPC_SERVER_ERROR_BLOCK_NOT_AVAILABLE
+			| JSON_RPC_SERVER_ERROR_BLOCK_STATUS_NOT_AVAILABLE_YET => {
+				return AppError::BlockNotAvailable(slot)
+			}
+			_ => {}
+		}
+	}
+
+	classify_error(error)
+}
+
+/// Retries requests that may succeed when sent again with exponential backoff and jitter.
+/// Skipped slots and RPC errors are final, rate limits are left to the rpc sender which waits
+/// for `Retry-After` and to failover. Blocks that are not available yet are re-queued by the rpc
+/// workers instead so that the request does not hold a worker.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+	max_retries: u32,
+	base_delay: Duration,
+	max_delay: Duration,
+}
+
+impl RetryPolicy {
+	pub fn new(config: &RetryConfig) -> Self {
+		Self {
+			max_retries: config.max_retries,
+			base_delay: Duration::from_millis(config.base_delay_ms),
+			max_delay: Duration::from_millis(config.max_delay_ms),
+		}
+	}
+
+	/// Whether a request that failed with `error` after `retries` retries is retried
+	pub fn should_retry(&self, error: &AppError, retries: u32) -> bool {
+		retries < self.max_retries && is_transient(error)
+	}
+
+	/// Delay before retry number `retry`, starting at one
+	pub fn delay(&self, retry: u32) -> Duration {
+		let backoff = self
+			.base_delay
+			.saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+			.min(self.max_delay);
+		let jitter = rand::thread_rng().gen_range(0..=backoff.as_millis() as u64 / 4);
+
+		backoff + Duration::from_millis(jitter)
+	}
+}
+
+fn is_transient(error: &AppError) -> bool {
+	match error {
+		AppError::TimeoutError => true,
+		AppError::SolanaClientError(e) => !matches!(e.kind(), ClientErrorKind::RpcError(_)),
+		_ => false,
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use solana_client::rpc_request::RpcResponseErrorData;
+
+	fn response_error(code: i64) -> ClientError {
+		RpcError::RpcResponseError {
+			code,
+			message: String::new(),
+			data: RpcResponseErrorData::Empty,
+		}
+		.into()
+	}
+
+	#[test]
+	fn test_classify_block_error() {
+		let policy = RetryPolicy::new(&RetryConfig::default());
+
+		let skipped = classify_block_error(response_error(JSON_RPC_SERVER_ERROR_SLOT_SKIPPED), 10);
+		assert!(matches!(skipped, AppError::SlotSkipped(10)));
+		assert!(!policy.should_retry(&skipped, 0));
+
+		let not_available = classify_block_error(
+			response_error(JSON_RPC_SERVER_ERROR_BLOCK_NOT_AVAILABLE),
+			11,
+		);
+		assert!(matches!(not_available, AppError::BlockNotAvailable(11)));
+		assert!(!policy.should_retry(&not_available, 0));
+
+		let invalid_params = classify_block_error(response_error(-32602), 12);
+		assert!(matches!(invalid_params, AppError::SolanaClientError(_)));
+		assert!(!policy.should_retry(&invalid_params, 0));
+	}
+}
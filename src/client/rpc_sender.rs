@@ -0,0 +1,129 @@
+use super::rate_limiter::RateLimiter;
+use async_trait::async_trait;
+use log::warn;
+use reqwest::{
+	header::{HeaderMap, RETRY_AFTER},
+	StatusCode,
+};
+use serde::Deserialize;
+use serde_json::Value;
+use solana_client::{
+	client_error::Result as ClientResult,
+	rpc_request::{RpcError, RpcRequest, RpcResponseErrorData},
+	rpc_sender::{RpcSender, RpcTransportStats},
+};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Wait applied when a rate limited response has no usable `Retry-After`
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(1);
+
+/// Timeout of each HTTP request, waits for the rate limiter and `Retry-After` are not included
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Idle connections are kept open this long so that requests reuse them instead of repeating
+/// the TLS handshake
+const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
+const TCP_KEEPALIVE: Duration = Duration::from_secs(60);
+
+#[derive(Deserialize)]
+struct RpcErrorObject {
+	code: i64,
+	message: String,
+}
+
+/// JSON-RPC over HTTP sender for `RpcClient` that takes a token from the endpoint's
+/// `RateLimiter` before every request. Rate limited responses pause the limiter for the
+/// `Retry-After` they carry, which holds back every worker sending to the endpoint, and are
+/// retried up to `max_rate_limited_retries` times before the 429 is returned. Connections are
+/// pooled and kept alive, HTTP/2 is negotiated with endpoints that support it.
+pub struct RateLimitedSender {
+	client: reqwest::Client,
+	url: String,
+	limiter: Arc<RateLimiter>,
+	max_rate_limited_retries: u32,
+	request_id: AtomicU64,
+}
+
+impl RateLimitedSender {
+	pub fn new(url: String, limiter: Arc<RateLimiter>, max_rate_limited_retries: u32) -> Self {
+		let client = reqwest::Client::builder()
+			.timeout(REQUEST_TIMEOUT)
+			.pool_idle_timeout(POOL_IDLE_TIMEOUT)
+			.tcp_keepalive(TCP_KEEPALIVE)
+			.build()
+			.expect("Failed to build http client");
+
+		Self {
+			client,
+			url,
+			limiter,
+			max_rate_limited_retries,
+			request_id: AtomicU64::new(0),
+		}
+	}
+}
+
+#[async_trait]
+impl RpcSender for RateLimitedSender {
+	async fn send(&self, request: RpcRequest, params: Value) -> ClientResult<Value> {
+		let mut retries = 0;
+		loop {
+			self.limiter.acquire().await;
+
+			let request_id = self.request_id.fetch_add(1, Ordering::Relaxed);
+			let body = request.build_request_json(request_id, params.clone());
+			let response = self.client.post(&self.url).json(&body).send().await?;
+
+			if response.status() == StatusCode::TOO_MANY_REQUESTS {
+				let retry_after = retry_after(response.headers()).unwrap_or(DEFAULT_RETRY_AFTER);
+				self.limiter.pause(retry_after);
+				if retries < self.max_rate_limited_retries {
+					retries += 1;
+					warn!(
+						"[RPC] {} rate limited, retrying in {:?} (attempt {})",
+						request, retry_after, retries
+					);
+					continue;
+				}
+			}
+
+			let mut json: Value = response.error_for_status()?.json().await?;
+			if let Some(error) = json.get("error") {
+				let error: RpcErrorObject = serde_json::from_value(error.clone()).map_err(|e| {
+					RpcError::RpcRequestError(format!("Failed to parse rpc error: {}", e))
+				})?;
+				return Err(RpcError::RpcResponseError {
+					code: error.code,
+					message: error.message,
+					data: RpcResponseErrorData::Empty,
+				}
+				.into());
+			}
+
+			return Ok(json["result"].take());
+		}
+	}
+
+	fn get_transport_stats(&self) -> RpcTransportStats {
+		RpcTransportStats::default()
+	}
+
+	fn url(&self) -> String {
+		self.url.clone()
+	}
+}
+
+/// `Retry-After` in seconds, the HTTP date form is not used by RPC providers
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+	headers
+		.get(RETRY_AFTER)?
+		.to_str()
+		.ok()?
+		.trim()
+		.parse()
+		.ok()
+		.map(Duration::from_secs)
+}
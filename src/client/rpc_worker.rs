@@ -1,28 +1,111 @@
+use super::endpoints::EndpointPool;
+use super::latency::LatencyHistogram;
 use super::Gettable;
-use crate::config::ClientConfig;
-use crate::error::Result;
+use crate::backfill::record_gaps;
+use crate::channel::{StageReceiver, StageSender};
+use crate::config::{ClientConfig, RequeueConfig};
+use crate::database::DatabasePool;
+use crate::error::{AppError, Result};
 use crate::pool::ThreadPool;
 use crate::worker::{Worker, WorkerHandle, WorkerManager};
+use crossbeam_channel::{RecvError, RecvTimeoutError};
 use log::info;
+use std::collections::VecDeque;
 use std::future::Future;
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::time::interval;
+
+/// Request taken from the rpc channel or re-queued because its block was not available yet
+struct Pending<T> {
+	input: T,
+	attempts: u32,
+	ready_at: Instant,
+}
+
+impl<T> Pending<T> {
+	fn new(input: T) -> Self {
+		Self {
+			input,
+			attempts: 0,
+			ready_at: Instant::now(),
+		}
+	}
+
+	/// The next attempt of a request whose block was not available, made after `delay`. `None`
+	/// once `max_attempts` have been made
+	fn requeue(self, max_attempts: u32, delay: Duration) -> Option<Self> {
+		let attempts = self.attempts + 1;
+		(attempts < max_attempts).then(|| Self {
+			input: self.input,
+			attempts,
+			ready_at: Instant::now() + delay,
+		})
+	}
+}
+
+/// Re-queued requests shared by the workers of a manager, bounded by `[client.requeue]`
+/// `capacity`. Every request waits the same delay so requests are kept in the order they become
+/// ready.
+struct DelayQueue<T> {
+	queue: Mutex<VecDeque<Pending<T>>>,
+	config: RequeueConfig,
+}
+
+impl<T> DelayQueue<T> {
+	fn new(config: RequeueConfig) -> Self {
+		Self {
+			queue: Mutex::new(VecDeque::new()),
+			config,
+		}
+	}
+
+	/// Queues the request, it is handed back if the queue is full
+	fn push(&self, pending: Pending<T>) -> std::result::Result<(), Pending<T>> {
+		let mut queue = self.queue.lock().unwrap();
+		if queue.len() >= self.config.capacity {
+			return Err(pending);
+		}
+		queue.push_back(pending);
+		Ok(())
+	}
+
+	/// Takes the first request if it is ready, otherwise returns when it will be
+	fn pop_ready(&self, now: Instant) -> std::result::Result<Pending<T>, Option<Instant>> {
+		let mut queue = self.queue.lock().unwrap();
+		match queue.front() {
+			Some(pending) if pending.ready_at <= now => Ok(queue.pop_front().unwrap()),
+			Some(pending) => Err(Some(pending.ready_at)),
+			None => Err(None),
+		}
+	}
+}
 
 /// Manages the pool of `RpcWorkers`s. Crossbeam channel is cloned to every
 /// worker to continuously retrieve blocks without creating a backlog. 
 /// On a free plan with Helius, it takes longer than 400ms to retrieve one block, testing on my
-/// connection/laptop requires five(5) rpc workers to ensure no backlog of requests. Backlogging
-/// requests can lead to a memory leak as the crossbeam channels are unbounded.
+/// connection/laptop requires five(5) rpc workers to ensure no backlog of requests. Channels are
+/// bounded by `[channels]` in `Config.toml`, a backlog is handled by the stage's overflow policy.
+/// Requests are balanced across the `EndpointPool` which is shared by every worker and every
+/// other RPC consumer. Requests for blocks that are not available yet are re-queued on a bounded
+/// delay queue shared by the workers until the block is retrieved or the slot is skipped. The
+/// time taken to retrieve each block, failover and retries included, is recorded in a latency
+/// histogram whose percentiles are logged every `[client.failover]` `metrics_interval_secs`.
 pub struct RpcWorkerManager<T>
 where
 	T: Gettable,
 	T::Output: Send,
 {
-	config: ClientConfig,
+	endpoints: Arc<EndpointPool>,
+	db_pool: DatabasePool,
+	requeued: Arc<DelayQueue<T>>,
+	latency: Arc<LatencyHistogram>,
+	metrics_interval_secs: u64,
 	pool: Arc<ThreadPool>,
 	workers: Vec<WorkerHandle>,
-	rpc_rx: crossbeam_channel::Receiver<T>,
-	proc_tx: crossbeam_channel::Sender<T::Output>,
+	rpc_rx: StageReceiver<T>,
+	proc_tx: StageSender<T::Output>,
 }
 
 impl<T> RpcWorkerManager<T>
@@ -31,15 +114,22 @@ where
 	T::Output: Send + 'static,
 {
 	pub fn new(
-		config: ClientConfig,
-		rpc_rx: crossbeam_channel::Receiver<T>,
-		proc_tx: crossbeam_channel::Sender<T::Output>,
+		config: &ClientConfig,
+		endpoints: Arc<EndpointPool>,
+		db_pool: DatabasePool,
+		rpc_rx: StageReceiver<T>,
+		proc_tx: StageSender<T::Output>,
 		worker_threads: usize,
 	) -> Self {
 		let pool = Arc::new(ThreadPool::new(worker_threads));
+		info!("Rpc endpoints: {}", endpoints.len());
 
 		Self {
-			config,
+			endpoints,
+			db_pool,
+			requeued: Arc::new(DelayQueue::new(config.requeue.clone())),
+			latency: Arc::new(LatencyHistogram::default()),
+			metrics_interval_secs: config.failover.metrics_interval_secs,
 			pool,
 			workers: Vec::with_capacity(worker_threads),
 			rpc_rx,
@@ -56,6 +146,11 @@ where
 
 	pub async fn run(&mut self) -> Result<()> {
 		self.initialize().await;
+		let _latency_handle = tokio::spawn(Self::report_latency(
+			self.latency.clone(),
+			self.metrics_interval_secs,
+		));
+
 		let ctrl_c = tokio::spawn(async {
 			tokio::signal::ctrl_c()
 				.await
@@ -69,6 +164,31 @@ where
 
 		Ok(())
 	}
+
+	/// Logs the block latency percentiles of the workers every `interval_secs` until ctrl+c is
+	/// received
+	async fn report_latency(latency: Arc<LatencyHistogram>, interval_secs: u64) {
+		if interval_secs == 0 {
+			return;
+		}
+		let name = std::any::type_name::<T>()
+			.rsplit("::")
+			.next()
+			.unwrap_or_default();
+		let mut ticker = interval(Duration::from_secs(interval_secs));
+		ticker.tick().await;
+
+		loop {
+			tokio::select! {
+				_ = ticker.tick() => {
+					if let Some(summary) = latency.take_summary() {
+						log::info!("[RPC] {} latency: {}", name, summary);
+					}
+				}
+				_ = tokio::signal::ctrl_c() => break,
+			}
+		}
+	}
 }
 
 impl<T> WorkerManager for RpcWorkerManager<T>
@@ -79,7 +199,10 @@ where
 	fn spawn_worker(&mut self) -> Pin<Box<dyn Future<Output = ()> + Send + '_>> {
 		Box::pin(async move {
 			let worker = RpcClientWorker::new(
-				self.config.clone(),
+				self.endpoints.clone(),
+				self.db_pool.clone(),
+				self.requeued.clone(),
+				self.latency.clone(),
 				self.rpc_rx.clone(),
 				self.proc_tx.clone(),
 				self.pool.clone(),
@@ -119,33 +242,116 @@ pub struct RpcClientWorker<T: Gettable>
 where
 	T::Output: Send,
 {
-	pub config: ClientConfig,
-	pub rpc_rx: crossbeam_channel::Receiver<T>,
-	pub proc_tx: crossbeam_channel::Sender<T::Output>,
+	pub endpoints: Arc<EndpointPool>,
+	db_pool: DatabasePool,
+	requeued: Arc<DelayQueue<T>>,
+	latency: Arc<LatencyHistogram>,
+	pub rpc_rx: StageReceiver<T>,
+	pub proc_tx: StageSender<T::Output>,
 }
 
 impl<T: Gettable> RpcClientWorker<T>
 where
 	T::Output: Send + 'static,
 {
-	pub fn new(
-		config: ClientConfig,
-		rpc_rx: crossbeam_channel::Receiver<T>,
-		proc_tx: crossbeam_channel::Sender<T::Output>,
+	#[allow(clippy::new_ret_no_self)]
+	fn new(
+		endpoints: Arc<EndpointPool>,
+		db_pool: DatabasePool,
+		requeued: Arc<DelayQueue<T>>,
+		latency: Arc<LatencyHistogram>,
+		rpc_rx: StageReceiver<T>,
+		proc_tx: StageSender<T::Output>,
 		thread_pool: Arc<ThreadPool>,
 	) -> WorkerHandle {
-		let url = config.get_url();
-		info!("Url: {}", url);
-
 		WorkerHandle::new(
 			Self {
-				config,
+				endpoints,
+				db_pool,
+				requeued,
+				latency,
 				rpc_rx,
 				proc_tx,
 			},
 			thread_pool,
 		)
 	}
+
+	/// Takes a re-queued request once it is ready, otherwise waits for a new request until
+	/// then
+	fn recv(&self) -> std::result::Result<Pending<T>, RecvError> {
+		loop {
+			let ready_at = match self.requeued.pop_ready(Instant::now()) {
+				Ok(pending) => return Ok(pending),
+				Err(ready_at) => ready_at,
+			};
+
+			let received = match ready_at {
+				Some(ready_at) => self.rpc_rx.recv_deadline(ready_at),
+				None => self
+					.rpc_rx
+					.recv()
+					.map_err(|_| RecvTimeoutError::Disconnected),
+			};
+			match received {
+				Ok(input) => return Ok(Pending::new(input)),
+				Err(RecvTimeoutError::Timeout) => continue,
+				// Requests already re-queued are still made once the rpc channel closes
+				Err(RecvTimeoutError::Disconnected) => match ready_at {
+					Some(ready_at) => {
+						std::thread::sleep(ready_at.saturating_duration_since(Instant::now()))
+					}
+					None => return Err(RecvError),
+				},
+			}
+		}
+	}
+
+	/// Queues the request for another attempt after `[client.requeue]` `delay_ms`. Slots given
+	/// up on after `max_attempts` or because the queue is full are recorded as missing in
+	/// `slot_gaps` for the backfill task.
+	async fn requeue(&self, pending: Pending<T>, error: AppError) {
+		let gap_slot = pending.input.gap_slot();
+		let config = &self.requeued.config;
+		let delay = Duration::from_millis(config.delay_ms);
+
+		match pending.requeue(config.max_attempts, delay) {
+			Some(pending) => {
+				let attempts = pending.attempts;
+				match self.requeued.push(pending) {
+					Ok(()) => {
+						log::debug!("{}, re-queueing (attempt {})", error, attempts);
+						return;
+					}
+					Err(_) => log::warn!("{}, re-queue is full, giving up", error),
+				}
+			}
+			None => log::warn!(
+				"{} after {} attempts, giving up",
+				error,
+				config.max_attempts
+			),
+		}
+
+		self.record_gap(gap_slot).await;
+	}
+
+	/// Records the slot of a request that was given up on as missing in `slot_gaps` so that the
+	/// backfill task retrieves it
+	async fn record_gap(&self, gap_slot: Option<u64>) {
+		if let Some(slot) = gap_slot {
+			if let Err(e) = record_gaps(&self.db_pool, &[slot], "missing").await {
+				log::error!("Could not record slot {} as missing: {}", slot, e);
+			}
+		}
+	}
+
+	/// Sends the request to the endpoints picked by the `EndpointPool` until one succeeds
+	async fn get_with_failover(&self, input: T) -> Result<T::Output> {
+		self.endpoints
+			.request(move |endpoint| T::get(input.clone(), endpoint))
+			.await
+	}
 }
 
 impl<T: Gettable> Worker for RpcClientWorker<T>
@@ -155,17 +361,31 @@ where
 	fn run(self) -> Pin<Box<dyn Future<Output = Result<()>> + Send + 'static>> {
 		Box::pin(async move {
 			loop {
-				match self.rpc_rx.recv() {
-					Ok(data) => {
+				match self.recv() {
+					Ok(pending) => {
 						log::debug!("WS -> RPC queue length: {}", self.rpc_rx.len());
-						let output = match T::get(data, &self.config).await {
-							Ok(output) => output,
+						let started = Instant::now();
+						let output = match self.get_with_failover(pending.input.clone()).await {
+							Ok(output) => {
+								self.latency.record(started.elapsed());
+								output
+							}
+							// Skipped slots have no block to retrieve
+							Err(e @ AppError::SlotSkipped(_)) => {
+								log::debug!("{}", e);
+								continue;
+							}
+							Err(e @ AppError::BlockNotAvailable(_)) => {
+								self.requeue(pending, e).await;
+								continue;
+							}
 							Err(e) => {
-								log::error!("Error getting block: {}", e);
+								log::error!("Error getting block: {}, giving up", e);
+								self.record_gap(pending.input.gap_slot()).await;
 								continue;
 							}
 						};
-						match self.proc_tx.send(output) {
+						match self.proc_tx.send_async(output).await {
 							Ok(_) => continue,
 							Err(e) => {
 								log::error!("Error sending to processor: {}", e);
@@ -182,3 +402,40 @@ where
 		})
 	}
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_requeue_until_max_attempts() {
+		let mut pending = Pending::new(10u64);
+		for attempts in 1..3 {
+			pending = pending.requeue(3, Duration::ZERO).unwrap();
+			assert_eq!(pending.attempts, attempts);
+			assert_eq!(pending.input, 10);
+		}
+		assert!(pending.requeue(3, Duration::ZERO).is_none());
+	}
+
+	#[test]
+	fn test_delay_queue_is_bounded_and_waits_for_delay() {
+		let queue = DelayQueue::new(RequeueConfig {
+			capacity: 2,
+			..Default::default()
+		});
+		let now = Instant::now();
+		let delay = Duration::from_secs(60);
+		for input in [1u64, 2] {
+			let pending = Pending::new(input).requeue(5, delay).unwrap();
+			assert!(queue.push(pending).is_ok());
+		}
+		assert!(queue.push(Pending::new(3u64)).is_err());
+
+		let ready_at = queue.pop_ready(now).err().unwrap().unwrap();
+		assert!(ready_at >= now + delay);
+		assert_eq!(queue.pop_ready(ready_at).ok().unwrap().input, 1);
+		assert_eq!(queue.pop_ready(now + delay * 2).ok().unwrap().input, 2);
+		assert_eq!(queue.pop_ready(now).err(), Some(None));
+	}
+}
@@ -3,14 +3,24 @@ use crossbeam_channel::Receiver;
 use solana_client::pubsub_client::PubsubClientSubscription;
 use serde::de::DeserializeOwned;
 
+/// Subscription handle and the receiver of its updates
+pub type Subscription<T> = (PubsubClientSubscription<T>, Receiver<T>);
+
 /// Trait to ensure that websocket clients can be generic over any type that implements
 /// this trait
 pub trait Subscribable: Sized + 'static {
 	type Output: DeserializeOwned + Send;
-	fn subscribe(
-		config: &ClientConfig,
-	) -> Result<(
-		PubsubClientSubscription<Self::Output>,
-		Receiver<Self::Output>,
-	)>;
+	fn subscribe(config: &ClientConfig) -> Result<Subscription<Self::Output>>;
+}
+
+/// Converts a subscription update into the item sent to the next pipeline stage, updates are
+/// passed through unchanged by default. Returns `None` if the update carries nothing to send.
+pub trait IntoStageItem<U> {
+	fn into_stage_item(self) -> Option<U>;
+}
+
+impl<T> IntoStageItem<T> for T {
+	fn into_stage_item(self) -> Option<T> {
+		Some(self)
+	}
 }
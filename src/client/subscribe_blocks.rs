@@ -1,15 +1,17 @@
-use super::Subscribable;
+use super::{IntoStageItem, Subscribable};
 use crate::{config::ClientConfig, error::Result, models::BlockUpdate};
 use crossbeam_channel::Receiver;
-use log::info;
+use log::{info, warn};
 use solana_client::{
 	pubsub_client::{BlockSubscription, PubsubClient, PubsubClientSubscription},
 	rpc_config::{RpcBlockSubscribeConfig, RpcBlockSubscribeFilter},
+	rpc_response::SlotInfo,
 };
 use solana_sdk::commitment_config::CommitmentConfig;
-use solana_transaction_status::{TransactionDetails, UiTransactionEncoding};
+use solana_transaction_status::{TransactionDetails, UiConfirmedBlock, UiTransactionEncoding};
 
-/// Not used - Block subscription is not available on free plans
+/// Subscribes to full confirmed blocks, used when `ingestion = "blocks"`. Block subscription is
+/// not available on free plans.
 impl Subscribable for BlockSubscription {
 	type Output = BlockUpdate;
 
@@ -19,27 +21,75 @@ impl Subscribable for BlockSubscription {
 		PubsubClientSubscription<Self::Output>,
 		Receiver<Self::Output>,
 	)> {
-		let url = if !config.api_key.is_empty() {
-			format!("{}?api-key={}", config.url, config.api_key)
-		} else {
-			config.url.clone()
-		};
-		info!("Url: {}", url);
+		let url = config.get_ws_url();
 
+		// Same block contents as `getBlock` in `SlotInfo::get` so that both ingestion modes
+		// produce the same `ProcessedBlock`
 		let block_filter = RpcBlockSubscribeFilter::All;
 		let block_config = RpcBlockSubscribeConfig {
 			commitment: Some(CommitmentConfig::confirmed()),
 			encoding: Some(UiTransactionEncoding::Json),
 			transaction_details: Some(TransactionDetails::Full),
-			show_rewards: Some(false),
-			max_supported_transaction_version: None,
+			show_rewards: Some(true),
+			max_supported_transaction_version: Some(0),
 		};
 
 		info!("Subscribing to blocks...");
-		let (_subscription, rx) =
+		let (block_subscription, block_rx) =
 			PubsubClient::block_subscribe(url.as_str(), block_filter, Some(block_config))?;
 		info!("Subscribed to blocks");
 
-		Ok((_subscription, rx))
+		Ok((block_subscription, block_rx))
+	}
+}
+
+/// Block updates are sent to the processing workers in the same form the rpc workers produce,
+/// updates without a block or with an error are dropped and left to the backfill task
+impl IntoStageItem<(SlotInfo, UiConfirmedBlock)> for BlockUpdate {
+	fn into_stage_item(self) -> Option<(SlotInfo, UiConfirmedBlock)> {
+		let update = self.value;
+		if let Some(err) = update.err {
+			warn!("Block update for slot {} failed: {:?}", update.slot, err);
+			return None;
+		}
+
+		let block = update.block?;
+		let slot_info = SlotInfo {
+			slot: update.slot,
+			parent: block.parent_slot,
+			root: 0,
+		};
+
+		Some((slot_info, block))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use serde_json::json;
+
+	#[test]
+	fn test_block_update_into_processor_input() {
+		let update: BlockUpdate = serde_json::from_value(json!({
+			"context": { "slot": 101 },
+			"value": {
+				"slot": 100,
+				"block": {
+					"previousBlockhash": "11111111111111111111111111111111",
+					"blockhash": "11111111111111111111111111111111",
+					"parentSlot": 99,
+					"blockTime": 1700000000,
+				},
+				"err": null,
+			},
+		}))
+		.unwrap();
+
+		let (slot_info, block): (SlotInfo, UiConfirmedBlock) = update.into_stage_item().unwrap();
+
+		assert_eq!(slot_info.slot, 100);
+		assert_eq!(slot_info.parent, 99);
+		assert_eq!(block.block_time, Some(1700000000));
 	}
 }
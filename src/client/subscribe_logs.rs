@@ -1,33 +1,184 @@
-use super::Subscribable;
-use crate::{config::ClientConfig, error::Result};
-use crossbeam_channel::Receiver;
-use log::info;
+use super::get_transaction::WatchedSignature;
+use super::ws::{supervise, ConnectionStatus};
+use crate::{
+	channel::StageSender,
+	config::{ClientConfig, WatcherCommitment, WatcherConfig},
+	error::{AppError, Result},
+};
+use crossbeam_channel::{bounded, Receiver};
+use log::{error, info};
 use solana_client::{
-	pubsub_client::{LogsSubscription, PubsubClient, PubsubClientSubscription},
+	pubsub_client::{PubsubClient, PubsubLogsClientSubscription},
 	rpc_config::{RpcTransactionLogsConfig, RpcTransactionLogsFilter},
+	rpc_response::{Response, RpcLogsResponse},
 };
 use solana_sdk::commitment_config::CommitmentConfig;
+use std::collections::{HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+
+/// Watches the logs of the programs and accounts listed under `[watcher]`, used when
+/// `ingestion = "logs"`. `logsSubscribe` accepts a single address per subscription so one is
+/// opened per address, the signature of every matching transaction is sent to the rpc workers
+/// that fetch it with `getTransaction`. Each subscription is supervised like `WsClient`'s and is
+/// opened again with backoff when it closes or stalls. ctrl+c handler implemented for graceful
+/// shutdown.
+pub struct LogsWatcher {
+	pub config: ClientConfig,
+	pub watcher: WatcherConfig,
+	pub tx: StageSender<WatchedSignature>,
+}
+
+impl LogsWatcher {
+	pub fn new(
+		config: ClientConfig,
+		watcher: WatcherConfig,
+		tx: StageSender<WatchedSignature>,
+	) -> Self {
+		Self {
+			config,
+			watcher,
+			tx,
+		}
+	}
+
+	/// Starts a supervised subscription per watched address with ctrl+c for shutdown
+	pub async fn subscribe(&self) -> Result<()> {
+		if self.watcher.mentions.is_empty() {
+			return Err(AppError::Unknown(
+				"No addresses to watch under [watcher]".to_string(),
+			));
+		}
+
+		let commitment = match self.watcher.commitment {
+			WatcherCommitment::Confirmed => CommitmentConfig::confirmed(),
+			WatcherCommitment::Finalized => CommitmentConfig::finalized(),
+		};
+		info!(
+			"Watching logs of {} addresses...",
+			self.watcher.mentions.len()
+		);
+
+		// Dropping the sender disconnects the stop channel of every subscription
+		let (stop_tx, stop_rx) = bounded::<()>(1);
+		let recent = Arc::new(Mutex::new(RecentSignatures::new(
+			self.watcher.dedup_capacity,
+		)));
 
-impl Subscribable for LogsSubscription {
-	type Output = RawTransactionLogs;
+		for address in &self.watcher.mentions {
+			let config = self.config.clone();
+			let address = address.clone();
+			let tx = self.tx.clone();
+			let recent = recent.clone();
+			let stop_rx = stop_rx.clone();
+			tokio::task::spawn_blocking(move || {
+				supervise(
+					&config.ws,
+					&ConnectionStatus::default(),
+					&stop_rx,
+					|| Self::subscribe_address(&config, &address, commitment),
+					|response| Self::send_signature(&tx, &recent, response, commitment),
+				);
+			});
+		}
 
-	fn subscribe(
+		tokio::signal::ctrl_c().await?;
+		info!("Ctrl+C received, shutting down logs watcher...");
+		drop(stop_tx);
+
+		Ok(())
+	}
+
+	fn subscribe_address(
 		config: &ClientConfig,
+		address: &str,
+		commitment: CommitmentConfig,
 	) -> Result<(
-		PubsubClientSubscription<Self::Output>,
-		Receiver<Self::Output>,
+		PubsubLogsClientSubscription,
+		Receiver<Response<RpcLogsResponse>>,
 	)> {
-        let url = config.get_ws_url();
+		let url = config.get_ws_url();
 
-		let log_filter = RpcTransactionLogsFilter::Mentions(vec!["675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8".to_string()]);
+		let log_filter = RpcTransactionLogsFilter::Mentions(vec![address.to_string()]);
 		let log_config = RpcTransactionLogsConfig {
-			commitment: Some(CommitmentConfig::finalized()),
+			commitment: Some(commitment),
 		};
 
-		info!("Subscribing to logs...");
+		info!("Subscribing to logs of {}...", address);
 		let (log_subscription, log_rx) =
 			PubsubClient::logs_subscribe(url.as_str(), log_filter, log_config)?;
 
 		Ok((log_subscription, log_rx))
 	}
+
+	/// Sends the signature to the rpc workers, a transaction mentioning more than one watched
+	/// address is only sent once
+	fn send_signature(
+		tx: &StageSender<WatchedSignature>,
+		recent: &Mutex<RecentSignatures>,
+		response: Response<RpcLogsResponse>,
+		commitment: CommitmentConfig,
+	) {
+		let signature = response.value.signature;
+		if !recent.lock().unwrap().insert(&signature) {
+			return;
+		}
+		if let Err(e) = tx.send(WatchedSignature {
+			signature,
+			commitment,
+		}) {
+			error!("Error sending signature: {}", e);
+		}
+	}
+}
+
+/// Signatures seen most recently, the oldest is forgotten once `capacity` is reached
+pub struct RecentSignatures {
+	capacity: usize,
+	seen: HashSet<String>,
+	order: VecDeque<String>,
+}
+
+impl RecentSignatures {
+	pub fn new(capacity: usize) -> Self {
+		Self {
+			capacity: capacity.max(1),
+			seen: HashSet::new(),
+			order: VecDeque::new(),
+		}
+	}
+
+	/// Returns `false` if the signature was already seen
+	pub fn insert(&mut self, signature: &str) -> bool {
+		if self.seen.contains(signature) {
+			return false;
+		}
+
+		if self.order.len() == self.capacity {
+			if let Some(oldest) = self.order.pop_front() {
+				self.seen.remove(&oldest);
+			}
+		}
+		self.seen.insert(signature.to_string());
+		self.order.push_back(signature.to_string());
+
+		true
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_recent_signatures() {
+		let mut recent = RecentSignatures::new(2);
+
+		assert!(recent.insert("a"));
+		assert!(!recent.insert("a"));
+		assert!(recent.insert("b"));
+		assert!(recent.insert("c"));
+		// "a" was forgotten once "c" was inserted
+		assert!(recent.insert("a"));
+		assert!(!recent.insert("c"));
+	}
 }
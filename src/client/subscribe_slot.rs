@@ -69,6 +69,7 @@ mod test {
     use tokio::time::timeout;
 
     #[tokio::test]
+    #[ignore = "requires the websocket endpoint in Config.toml"]
     async fn test_subscribe() {
         let config = load_config("Config.toml").unwrap();
 
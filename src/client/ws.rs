@@ -1,71 +1,324 @@
-use super::Subscribable;
-use crate::config::ClientConfig;
+use super::retry::RetryPolicy;
+use super::{IntoStageItem, Subscribable};
+use crate::channel::StageSender;
+use crate::config::{ClientConfig, RetryConfig, WsConfig};
 use crate::error::Result;
-use crossbeam::channel::{bounded, Receiver};
-use log::{error, info};
+use crossbeam::channel::{after, bounded, never, Receiver};
+use log::{debug, error, info, warn};
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// State of the websocket connection, `Disconnected` while waiting to reconnect
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ConnectionState {
+	Connecting,
+	Connected,
+	Disconnected,
+	Stopped,
+}
+
+impl ConnectionState {
+	fn from_u8(value: u8) -> Self {
+		match value {
+			0 => Self::Connecting,
+			1 => Self::Connected,
+			2 => Self::Disconnected,
+			_ => Self::Stopped,
+		}
+	}
+}
+
+/// Connection state and reconnect count of a `WsClient`, shared with whoever reports them
+#[derive(Clone, Debug, Default)]
+pub struct ConnectionStatus {
+	state: Arc<AtomicU8>,
+	reconnects: Arc<AtomicU64>,
+}
+
+impl ConnectionStatus {
+	pub fn state(&self) -> ConnectionState {
+		ConnectionState::from_u8(self.state.load(Ordering::Relaxed))
+	}
+
+	pub fn reconnects(&self) -> u64 {
+		self.reconnects.load(Ordering::Relaxed)
+	}
+
+	fn set(&self, state: ConnectionState) {
+		if self.state.swap(state as u8, Ordering::Relaxed) != state as u8 {
+			info!(
+				"[WS] Connection state: {:?}, reconnects: {}",
+				state,
+				self.reconnects()
+			);
+		}
+	}
+}
+
+/// Why the receive loop returned
+#[derive(Debug)]
+enum Disconnect {
+	Closed,
+	Stalled,
+	Stopped,
+}
 
 /// Websocket client to listen for updates is generic over the trait `Subscribable` for reuse and
-/// extensibility ctrl+c handler implemented for graceful shutdown
-pub struct WsClient<T: Subscribable> {
+/// extensibility ctrl+c handler implemented for graceful shutdown. Updates are converted to `U`
+/// through `IntoStageItem` before being sent, slot updates go to the rpc workers as they are while
+/// block updates go straight to the processing workers. The subscription is supervised, when it
+/// closes or no update arrives for `[client.ws]` `stall_timeout_secs` it is opened again with
+/// exponential backoff.
+pub struct WsClient<T: Subscribable, U = <T as Subscribable>::Output> {
 	pub config: ClientConfig,
-	pub rpc_tx: crossbeam_channel::Sender<T::Output>,
+	pub tx: StageSender<U>,
+	status: ConnectionStatus,
+	subscription: PhantomData<T>,
 }
 
-impl<T: Subscribable> WsClient<T> {
-	pub fn new(config: ClientConfig, rpc_tx: crossbeam_channel::Sender<T::Output>) -> Self {
-		Self { config, rpc_tx }
+impl<T, U> WsClient<T, U>
+where
+	T: Subscribable,
+	T::Output: IntoStageItem<U>,
+	U: Send + 'static,
+{
+	pub fn new(config: ClientConfig, tx: StageSender<U>) -> Self {
+		Self {
+			config,
+			tx,
+			status: ConnectionStatus::default(),
+			subscription: PhantomData,
+		}
 	}
 
-    /// Starts the websocket subscription with ctrl+c for shutdown
-	pub async fn subscribe(&self) -> Result<()> {
-		let (_sub, rx) = T::subscribe(&self.config)?;
-		info!("Listening for updates...");
+	pub fn status(&self) -> ConnectionStatus {
+		self.status.clone()
+	}
 
+	/// Starts the websocket subscription with ctrl+c for shutdown
+	pub async fn subscribe(&self) -> Result<()> {
 		let (stop_tx, stop_rx) = bounded::<()>(1);
 
-		let rpc_tx = self.rpc_tx.clone();
+		let config = self.config.clone();
+		let tx = self.tx.clone();
+		let status = self.status.clone();
 		tokio::task::spawn_blocking(move || {
-			Self::receive_loop(rpc_tx, rx, stop_rx);
+			Self::supervise(&config, tx, &status, stop_rx);
 		});
 
 		tokio::signal::ctrl_c().await?;
 		info!("Ctrl+C received, shutting down websocket...");
-		let _ = stop_tx.send(());
+		drop(stop_tx);
 
 		Ok(())
 	}
 
-    /// Receive loop for subscribed data. Will just continue to the next loop if an error
-    /// is received. Stops on a stop signal
-	fn receive_loop(
-		rpc_tx: crossbeam_channel::Sender<T::Output>,
-		rx: Receiver<T::Output>,
+	/// Supervises the subscription of `T`, sending every update that converts into a stage
+	/// item. Returns once the stop channel is disconnected.
+	fn supervise(
+		config: &ClientConfig,
+		tx: StageSender<U>,
+		status: &ConnectionStatus,
 		stop_rx: Receiver<()>,
 	) {
-		loop {
-			crossbeam::select! {
-				recv(rx) -> result => {
-					match result {
-						Ok(response) => {
-							match rpc_tx.send(response) {
-							Ok(_) => continue,
-							Err(e) => {
-								error!("Error sending data: {}", e);
-								continue
-							}
-						};
-						}
-						Err(e) => {
-							error!("Subscription channel closed: {}", e);
-							break;
-						}
+		supervise(
+			&config.ws,
+			status,
+			&stop_rx,
+			|| T::subscribe(config),
+			|update: T::Output| {
+				let Some(item) = update.into_stage_item() else {
+					debug!("Update has nothing to send, skipping");
+					return;
+				};
+				if let Err(e) = tx.send(item) {
+					error!("Error sending data: {}", e);
+				}
+			},
+		);
+	}
+}
+
+/// Subscribes with `subscribe` and passes every update to `handle`, subscribing again after a
+/// backoff whenever the subscription closes or stalls. The backoff is reset once a connection
+/// delivered updates. Returns once the stop channel is disconnected.
+pub(crate) fn supervise<S, R>(
+	config: &WsConfig,
+	status: &ConnectionStatus,
+	stop_rx: &Receiver<()>,
+	mut subscribe: impl FnMut() -> Result<(S, Receiver<R>)>,
+	mut handle: impl FnMut(R),
+) where
+	S: Send + 'static,
+{
+	let backoff = RetryPolicy::new(&RetryConfig {
+		max_retries: 0,
+		base_delay_ms: config.reconnect_base_delay_ms,
+		max_delay_ms: config.reconnect_max_delay_ms,
+	});
+	let stall_timeout =
+		(config.stall_timeout_secs > 0).then(|| Duration::from_secs(config.stall_timeout_secs));
+	let mut attempt = 0;
+
+	loop {
+		status.set(ConnectionState::Connecting);
+		match subscribe() {
+			Ok((subscription, rx)) => {
+				status.set(ConnectionState::Connected);
+				info!("Listening for updates...");
+				let (disconnect, updates) = receive_loop(&rx, stop_rx, stall_timeout, &mut handle);
+
+				// Unsubscribing waits for the socket which a stalled connection may never
+				// release, so the subscription is dropped on its own thread
+				std::thread::spawn(move || drop(subscription));
+
+				match disconnect {
+					Disconnect::Stopped => break,
+					Disconnect::Closed => error!("Subscription channel closed"),
+					Disconnect::Stalled => {
+						warn!(
+							"No update received for {:?}",
+							stall_timeout.unwrap_or_default()
+						)
 					}
 				}
-				recv(stop_rx) -> _ => {
-					info!("Stop signal received, exiting received loop");
-					break;
+				if updates > 0 {
+					attempt = 0;
+				}
+			}
+			Err(e) => error!("Failed to subscribe: {}", e),
+		}
+
+		status.set(ConnectionState::Disconnected);
+		attempt += 1;
+		status.reconnects.fetch_add(1, Ordering::Relaxed);
+		let delay = backoff.delay(attempt);
+		info!(
+			"Reconnecting websocket in {:?} (attempt {})",
+			delay, attempt
+		);
+
+		crossbeam::select! {
+			recv(stop_rx) -> _ => break,
+			default(delay) => {}
+		}
+	}
+
+	info!("Stop signal received, websocket stopped");
+	status.set(ConnectionState::Stopped);
+}
+
+/// Receive loop for subscribed data, every update is passed to `handle`. Returns the reason it
+/// stopped and the number of updates received.
+fn receive_loop<R>(
+	rx: &Receiver<R>,
+	stop_rx: &Receiver<()>,
+	stall_timeout: Option<Duration>,
+	handle: &mut impl FnMut(R),
+) -> (Disconnect, u64) {
+	let mut updates = 0;
+	loop {
+		let stalled = stall_timeout.map(after).unwrap_or_else(never);
+		crossbeam::select! {
+			recv(rx) -> result => {
+				match result {
+					Ok(update) => {
+						updates += 1;
+						handle(update);
+					}
+					Err(_) => return (Disconnect::Closed, updates),
 				}
 			}
+			recv(stalled) -> _ => return (Disconnect::Stalled, updates),
+			recv(stop_rx) -> _ => return (Disconnect::Stopped, updates),
 		}
 	}
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::channel::stage_channel;
+	use crate::config::{OverflowPolicy, StageConfig};
+	use solana_client::{pubsub_client::SlotsSubscription, rpc_response::SlotInfo};
+	use std::net::{TcpListener, TcpStream};
+	use tungstenite::{accept, Message, WebSocket};
+
+	/// Accepts a slot subscription and confirms it, followed by a notification for `slot`
+	fn accept_subscription(listener: &TcpListener, slot: Option<u64>) -> WebSocket<TcpStream> {
+		let (stream, _) = listener.accept().unwrap();
+		let mut socket = accept(stream).unwrap();
+		socket.read().unwrap();
+		socket
+			.send(Message::text(r#"{"jsonrpc":"2.0","result":0,"id":1}"#))
+			.unwrap();
+		if let Some(slot) = slot {
+			let notification = format!(
+				r#"{{"jsonrpc":"2.0","method":"slotNotification","params":{{"result":{{"parent":{},"root":0,"slot":{}}},"subscription":0}}}}"#,
+				slot - 1,
+				slot
+			);
+			socket.send(Message::text(notification)).unwrap();
+		}
+		socket
+	}
+
+	#[test]
+	fn test_reconnects_after_close_and_stall() {
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let port = listener.local_addr().unwrap().port();
+		std::thread::spawn(move || {
+			// Closed right after the first update
+			drop(accept_subscription(&listener, Some(10)));
+			// Open but silent until the client gives up on it
+			let _stalled = accept_subscription(&listener, None);
+			let mut socket = accept_subscription(&listener, Some(20));
+			while socket.read().is_ok() {}
+		});
+
+		let config: ClientConfig = toml::from_str(&format!(
+			r#"
+			url = "localhost"
+			api_key = ""
+			worker_threads = 1
+
+			[ws]
+			url = "ws://127.0.0.1:{}"
+			stall_timeout_secs = 1
+			reconnect_base_delay_ms = 10
+			reconnect_max_delay_ms = 100
+			"#,
+			port
+		))
+		.unwrap();
+		let (tx, rx) = stage_channel::<SlotInfo>(
+			"WS",
+			&StageConfig {
+				capacity: 0,
+				overflow: OverflowPolicy::Block,
+			},
+		);
+		let status = ConnectionStatus::default();
+		let (stop_tx, stop_rx) = bounded::<()>(1);
+
+		let supervisor = {
+			let status = status.clone();
+			std::thread::spawn(move || {
+				WsClient::<SlotsSubscription>::supervise(&config, tx, &status, stop_rx)
+			})
+		};
+
+		let timeout = Duration::from_secs(10);
+		assert_eq!(rx.recv_timeout(timeout).unwrap().slot, 10);
+		assert_eq!(rx.recv_timeout(timeout).unwrap().slot, 20);
+		assert_eq!(status.state(), ConnectionState::Connected);
+		assert_eq!(status.reconnects(), 2);
+
+		drop(stop_tx);
+		supervisor.join().unwrap();
+		assert_eq!(status.state(), ConnectionState::Stopped);
+	}
+}
@@ -8,9 +8,21 @@ use toml;
 pub struct Config {
 	pub client: ClientConfig,
 	pub processor: WorkerConfig,
-	pub storage: WorkerConfig,
+	pub storage: StorageConfig,
 	pub database: DatabaseConfig,
 	pub server: ServerConfig,
+	#[serde(default)]
+	pub backfill: BackfillConfig,
+	#[serde(default)]
+	pub channels: ChannelConfig,
+	#[serde(default)]
+	pub resume: ResumeConfig,
+	#[serde(default)]
+	pub finalizer: FinalizerConfig,
+	#[serde(default)]
+	pub watcher: WatcherConfig,
+	#[serde(default)]
+	pub geyser: GeyserConfig,
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -18,11 +30,225 @@ pub struct ClientConfig {
 	pub url: String,
 	pub api_key: String,
     pub worker_threads: u32,
+	#[serde(default)]
+	pub ingestion: IngestionMode,
+	#[serde(default)]
+	pub endpoints: Vec<EndpointConfig>,
+	#[serde(default)]
+	pub failover: FailoverConfig,
+	#[serde(default)]
+	pub rate_limit: RateLimitConfig,
+	#[serde(default)]
+	pub retry: RetryConfig,
+	#[serde(default)]
+	pub requeue: RequeueConfig,
+	#[serde(default)]
+	pub ws: WsConfig,
+}
+
+/// Supervision of the websocket subscription. It is opened again when it closes or no update
+/// arrives for `stall_timeout_secs`, zero disables the stall check, with a delay doubling from
+/// `reconnect_base_delay_ms` up to `reconnect_max_delay_ms`. `url` replaces the websocket url
+/// derived from `url` and `api_key`, such as `ws://127.0.0.1:8900` for a local validator.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct WsConfig {
+	pub url: Option<String>,
+	pub stall_timeout_secs: u64,
+	pub reconnect_base_delay_ms: u64,
+	pub reconnect_max_delay_ms: u64,
+}
+
+impl Default for WsConfig {
+	fn default() -> Self {
+		Self {
+			url: None,
+			stall_timeout_secs: 10,
+			reconnect_base_delay_ms: 500,
+			reconnect_max_delay_ms: 30_000,
+		}
+	}
+}
+
+/// Blocks that are not available yet are requested again every `delay_ms` until they are
+/// retrieved or the slot turns out to be skipped. At most `capacity` requests wait at once, a
+/// slot is given up after `max_attempts` or when the queue is full and recorded as missing for
+/// the backfill task.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct RequeueConfig {
+	pub delay_ms: u64,
+	pub max_attempts: u32,
+	pub capacity: usize,
+}
+
+impl Default for RequeueConfig {
+	fn default() -> Self {
+		Self {
+			delay_ms: 500,
+			max_attempts: 30,
+			capacity: 1000,
+		}
+	}
+}
+
+/// Client side limit on the requests sent to each RPC endpoint, shared by every rpc worker. A
+/// `requests_per_sec` of zero disables the limit. Rate limited requests wait for the endpoint's
+/// `Retry-After` and are retried `max_retries` times.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+	pub requests_per_sec: f64,
+	pub burst: u32,
+	pub max_retries: u32,
+}
+
+impl Default for RateLimitConfig {
+	fn default() -> Self {
+		Self {
+			requests_per_sec: 10.0,
+			burst: 10,
+			max_retries: 3,
+		}
+	}
+}
+
+/// Retries of timeouts and connection errors, the delay doubles from `base_delay_ms` up to
+/// `max_delay_ms`
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct RetryConfig {
+	pub max_retries: u32,
+	pub base_delay_ms: u64,
+	pub max_delay_ms: u64,
+}
+
+impl Default for RetryConfig {
+	fn default() -> Self {
+		Self {
+			max_retries: 3,
+			base_delay_ms: 250,
+			max_delay_ms: 4000,
+		}
+	}
+}
+
+/// Additional RPC endpoint the rpc workers balance requests across alongside `url`, an endpoint
+/// with a higher `weight` receives proportionally more requests while it is healthy
+#[derive(Clone, Debug, Deserialize)]
+pub struct EndpointConfig {
+	pub url: String,
+	#[serde(default)]
+	pub api_key: String,
+	#[serde(default = "default_weight")]
+	pub weight: u32,
+}
+
+fn default_weight() -> u32 {
+	1
+}
+
+/// Health tracking of RPC endpoints. An endpoint that is rate limited, times out or fails
+/// `max_consecutive_failures` times in a row is skipped for `cooldown_secs`, per endpoint metrics
+/// are logged every `metrics_interval_secs`, zero disables them.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct FailoverConfig {
+	pub weight: u32,
+	pub cooldown_secs: u64,
+	pub max_consecutive_failures: u32,
+	pub metrics_interval_secs: u64,
+}
+
+impl Default for FailoverConfig {
+	fn default() -> Self {
+		Self {
+			weight: 1,
+			cooldown_secs: 30,
+			max_consecutive_failures: 3,
+			metrics_interval_secs: 60,
+		}
+	}
+}
+
+/// Where live blocks come from, `Slots` subscribes to slot notifications and fetches each block
+/// through the rpc workers while `Blocks` subscribes to full blocks with `blockSubscribe` and
+/// sends them straight to the processing workers. `Blocks` requires an RPC plan that supports
+/// block subscriptions. `Logs` only indexes transactions mentioning the addresses under
+/// `[watcher]`. `Geyser` streams blocks from the Yellowstone gRPC endpoint under `[geyser]` and
+/// requires the `geyser` feature.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum IngestionMode {
+	#[default]
+	Slots,
+	Blocks,
+	Logs,
+	Geyser,
+}
+
+/// Yellowstone gRPC endpoint used by `geyser` ingestion, `x_token` is sent as the `x-token`
+/// header when set. The stream is supervised like the websocket subscription, see `WsConfig`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct GeyserConfig {
+	pub endpoint: String,
+	pub x_token: Option<String>,
+	pub stall_timeout_secs: u64,
+	pub reconnect_base_delay_ms: u64,
+	pub reconnect_max_delay_ms: u64,
+}
+
+impl Default for GeyserConfig {
+	fn default() -> Self {
+		Self {
+			endpoint: String::new(),
+			x_token: None,
+			stall_timeout_secs: 10,
+			reconnect_base_delay_ms: 500,
+			reconnect_max_delay_ms: 30_000,
+		}
+	}
+}
+
+/// Settings for `logs` ingestion, each address in `mentions` is watched with its own
+/// `logsSubscribe` subscription and matching transactions are fetched by `worker_threads` rpc
+/// workers. `dedup_capacity` is the number of recent signatures remembered so that a transaction
+/// mentioning several watched addresses is only fetched once.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct WatcherConfig {
+	pub mentions: Vec<String>,
+	pub commitment: WatcherCommitment,
+	pub worker_threads: u32,
+	pub dedup_capacity: usize,
+}
+
+impl Default for WatcherConfig {
+	fn default() -> Self {
+		Self {
+			mentions: Vec::new(),
+			commitment: WatcherCommitment::Confirmed,
+			worker_threads: 2,
+			dedup_capacity: 10_000,
+		}
+	}
+}
+
+/// `getTransaction` does not serve `processed` transactions so only these two are accepted
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WatcherCommitment {
+	#[default]
+	Confirmed,
+	Finalized,
 }
 
 impl ClientConfig {
 	pub fn get_ws_url(&self) -> String {
-		if !self.api_key.is_empty() {
+		if let Some(url) = &self.ws.url {
+			url.clone()
+		} else if !self.api_key.is_empty() {
 			format!("wss://{}/?api-key={}", self.url, self.api_key)
 		} else {
 			format!("wss://{}/", self.url)
@@ -36,6 +262,26 @@ impl ClientConfig {
 			format!("https://{}/", self.url)
 		}
 	}
+
+	/// Every RPC endpoint with its weight, `url` first with `failover.weight` followed by
+	/// `endpoints`. The returned configs only differ in `url` and `api_key`.
+	pub fn get_endpoints(&self) -> Vec<(ClientConfig, u32)> {
+		let primary = ClientConfig {
+			endpoints: Vec::new(),
+			..self.clone()
+		};
+		let mut endpoints = vec![(primary.clone(), self.failover.weight)];
+		for endpoint in &self.endpoints {
+			let config = ClientConfig {
+				url: endpoint.url.clone(),
+				api_key: endpoint.api_key.clone(),
+				..primary.clone()
+			};
+			endpoints.push((config, endpoint.weight));
+		}
+
+		endpoints
+	}
 }
 
 #[derive(Debug, Deserialize)]
@@ -43,6 +289,23 @@ pub struct WorkerConfig {
 	pub worker_threads: u32,
 }
 
+#[derive(Clone, Debug, Deserialize)]
+pub struct StorageConfig {
+	pub worker_threads: u32,
+	#[serde(default)]
+	pub write_mode: WriteMode,
+}
+
+/// How storage workers write rows, `Insert` issues one statement per row while `Copy` streams
+/// every row of a block with a single binary `COPY`
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WriteMode {
+	Insert,
+	#[default]
+	Copy,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct DatabaseConfig {
 	pub user: String,
@@ -51,6 +314,8 @@ pub struct DatabaseConfig {
 	pub host: String,
 	pub pool_size: u32,
 	pub db_name: String,
+	#[serde(default)]
+	pub verify_schema_only: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -58,6 +323,124 @@ pub struct ServerConfig {
 	pub port: u16,
 }
 
+/// Settings for the backfill task which scans stored slots for gaps. If `start_slot` is not
+/// provided the scan starts from the lowest slot already in the database.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct BackfillConfig {
+	pub enabled: bool,
+	pub start_slot: Option<u64>,
+	pub interval_secs: u64,
+	pub batch_size: u64,
+	pub max_attempts: i32,
+	pub slot_margin: u64,
+}
+
+impl Default for BackfillConfig {
+	fn default() -> Self {
+		Self {
+			enabled: false,
+			start_slot: None,
+			interval_secs: 30,
+			batch_size: 1000,
+			max_attempts: 5,
+			slot_margin: 32,
+		}
+	}
+}
+
+/// Settings for resuming from the last committed slot on startup. `max_slots` limits how far
+/// back the catch up range goes after long downtime, the range is fetched by `worker_threads`
+/// rpc workers of its own while live slots are ingested. The checkpoint resumed from is advanced
+/// every `checkpoint_interval_secs`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct ResumeConfig {
+	pub enabled: bool,
+	pub max_slots: u64,
+	pub worker_threads: u32,
+	pub checkpoint_interval_secs: u64,
+}
+
+impl Default for ResumeConfig {
+	fn default() -> Self {
+		Self {
+			enabled: true,
+			max_slots: 10_000,
+			worker_threads: 2,
+			checkpoint_interval_secs: 10,
+		}
+	}
+}
+
+/// Settings for the finalizer task which marks stored blocks as finalized once the cluster has
+/// finalized their slot and deletes stored blocks that were not finalized, `batch_size` limits
+/// the number of slots checked per run.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct FinalizerConfig {
+	pub enabled: bool,
+	pub interval_secs: u64,
+	pub batch_size: i64,
+}
+
+impl Default for FinalizerConfig {
+	fn default() -> Self {
+		Self {
+			enabled: true,
+			interval_secs: 30,
+			batch_size: 10_000,
+		}
+	}
+}
+
+/// Capacities and overflow policies for the channels between pipeline stages, `rpc` feeds the
+/// rpc workers, `processor` the processing workers and `storage` the storage workers.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct ChannelConfig {
+	pub rpc: StageConfig,
+	pub processor: StageConfig,
+	pub storage: StageConfig,
+}
+
+impl Default for ChannelConfig {
+	fn default() -> Self {
+		Self {
+			rpc: StageConfig {
+				capacity: 1000,
+				overflow: OverflowPolicy::DropOldest,
+			},
+			processor: StageConfig {
+				capacity: 64,
+				overflow: OverflowPolicy::Block,
+			},
+			storage: StageConfig {
+				capacity: 64,
+				overflow: OverflowPolicy::Block,
+			},
+		}
+	}
+}
+
+/// A `capacity` of zero leaves the channel unbounded
+#[derive(Clone, Debug, Deserialize)]
+pub struct StageConfig {
+	pub capacity: usize,
+	#[serde(default)]
+	pub overflow: OverflowPolicy,
+}
+
+/// What a stage does when its downstream channel is full. `Block` waits until there is room,
+/// propagating backpressure upstream, `DropOldest` discards the oldest queued item. Spilling
+/// the overflow to disk is not supported, `Block` already bounds memory without it.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+	#[default]
+	Block,
+	DropOldest,
+}
 
 pub fn load_config(file_path: &str) -> Result<Config> {
 	let mut file = File::open(file_path)?;
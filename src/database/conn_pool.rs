@@ -1,3 +1,4 @@
+use super::run_migrations;
 use crate::{config::DatabaseConfig, error::Result};
 use bb8::Pool;
 use bb8_postgres::PostgresConnectionManager;
@@ -6,7 +7,9 @@ use tokio_postgres::{Config, NoTls};
 
 pub type DatabasePool = Arc<Pool<PostgresConnectionManager<NoTls>>>;
 
-/// Connection pool for database, wrapped in an Arc as this is shared between multiple processes
+/// Connection pool for database, wrapped in an Arc as this is shared between multiple processes.
+/// Embedded migrations are applied, or verified if `verify_schema_only` is set, before the pool
+/// is returned.
 pub async fn create_database_pool(config: &DatabaseConfig) -> Result<DatabasePool> {
 	let mut db_config = Config::new();
 	db_config
@@ -22,6 +25,9 @@ pub async fn create_database_pool(config: &DatabaseConfig) -> Result<DatabasePoo
 		.max_size(config.pool_size)
 		.build(mgr)
 		.await?;
+	let pool = Arc::new(pool);
 
-	Ok(Arc::new(pool))
+	run_migrations(&pool, config.verify_schema_only).await?;
+
+	Ok(pool)
 }
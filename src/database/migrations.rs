@@ -0,0 +1,244 @@
+use super::DatabasePool;
+use crate::error::{AppError, Result};
+
+/// A schema migration embedded in the binary
+pub struct Migration {
+	pub version: i32,
+	pub name: &'static str,
+	pub sql: &'static str,
+}
+
+/// Migrations in the order they are applied, versions must be contiguous starting from 1
+pub const MIGRATIONS: &[Migration] = &[
+	Migration {
+		version: 1,
+		name: "initial",
+		sql: include_str!("../../migrations/0001_initial.sql"),
+	},
+	Migration {
+		version: 2,
+		name: "transaction_status",
+		sql: include_str!("../../migrations/0002_transaction_status.sql"),
+	},
+	Migration {
+		version: 3,
+		name: "account_roles",
+		sql: include_str!("../../migrations/0003_account_roles.sql"),
+	},
+	Migration {
+		version: 4,
+		name: "lookup_table_accounts",
+		sql: include_str!("../../migrations/0004_lookup_table_accounts.sql"),
+	},
+	Migration {
+		version: 5,
+		name: "token_balance_changes",
+		sql: include_str!("../../migrations/0005_token_balance_changes.sql"),
+	},
+	Migration {
+		version: 6,
+		name: "lamport_balances",
+		sql: include_str!("../../migrations/0006_lamport_balances.sql"),
+	},
+	Migration {
+		version: 7,
+		name: "instructions",
+		sql: include_str!("../../migrations/0007_instructions.sql"),
+	},
+	Migration {
+		version: 8,
+		name: "decoded_instructions",
+		sql: include_str!("../../migrations/0008_decoded_instructions.sql"),
+	},
+	Migration {
+		version: 9,
+		name: "block_rewards",
+		sql: include_str!("../../migrations/0009_block_rewards.sql"),
+	},
+	Migration {
+		version: 10,
+		name: "block_lineage",
+		sql: include_str!("../../migrations/0010_block_lineage.sql"),
+	},
+	Migration {
+		version: 11,
+		name: "block_commitment",
+		sql: include_str!("../../migrations/0011_block_commitment.sql"),
+	},
+	Migration {
+		version: 12,
+		name: "transaction_inclusions",
+		sql: include_str!("../../migrations/0012_transaction_inclusions.sql"),
+	},
+	Migration {
+		version: 13,
+		name: "headerless_blocks",
+		sql: include_str!("../../migrations/0013_headerless_blocks.sql"),
+	},
+	Migration {
+		version: 14,
+		name: "unknown_transaction_status",
+		sql: include_str!("../../migrations/0014_unknown_transaction_status.sql"),
+	},
+	Migration {
+		version: 15,
+		name: "optional_block_time",
+		sql: include_str!("../../migrations/0015_optional_block_time.sql"),
+	},
+];
+
+/// Arbitrary key for the advisory lock held while migrating so that concurrently starting
+/// instances do not apply the same migration twice
+const MIGRATION_LOCK: i64 = 0x50_4c_44_45_52;
+
+/// Latest schema version known to this binary
+pub fn latest_version() -> i32 {
+	MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+/// Applies any migrations newer than the database's `schema_version`, or only verifies that the
+/// database is up to date if `verify_only` is set. Refuses to continue if the database has been
+/// migrated by a newer binary or still has the single `transaction_accounts` table of the
+/// schema that predates migrations.
+pub async fn run_migrations(db_pool: &DatabasePool, verify_only: bool) -> Result<()> {
+	let mut conn = db_pool.get().await?;
+
+	conn.batch_execute(
+		"CREATE TABLE IF NOT EXISTS schema_version (
+			version INT PRIMARY KEY,
+			name TEXT NOT NULL,
+			applied_at BIGINT NOT NULL DEFAULT EXTRACT(EPOCH FROM NOW())::BIGINT
+		)",
+	)
+	.await?;
+	conn.execute("SELECT pg_advisory_lock($1)", &[&MIGRATION_LOCK])
+		.await?;
+
+	let result = async {
+		let current: i32 = conn
+			.query_one("SELECT COALESCE(MAX(version), 0) FROM schema_version", &[])
+			.await?
+			.get(0);
+		let latest = latest_version();
+
+		if current == 0 && has_legacy_schema(&conn).await? {
+			return Err(AppError::LegacySchema);
+		}
+		if current > latest {
+			return Err(AppError::SchemaVersionAhead {
+				database: current,
+				binary: latest,
+			});
+		}
+		if current == latest {
+			log::info!("Database schema is up to date at version {}", current);
+			return Ok(());
+		}
+		if verify_only {
+			return Err(AppError::SchemaVersionBehind {
+				database: current,
+				binary: latest,
+			});
+		}
+
+		for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+			log::info!(
+				"Applying migration {}: {}",
+				migration.version,
+				migration.name
+			);
+			let transaction = conn.transaction().await?;
+			transaction.batch_execute(migration.sql).await?;
+			transaction
+				.execute(
+					"INSERT INTO schema_version (version, name) VALUES ($1, $2)",
+					&[&migration.version, &migration.name],
+				)
+				.await?;
+			transaction.commit().await?;
+		}
+
+		Ok::<(), AppError>(())
+	}
+	.await;
+
+	conn.execute("SELECT pg_advisory_unlock($1)", &[&MIGRATION_LOCK])
+		.await?;
+
+	result
+}
+
+/// Whether `transaction_accounts` exists before any migration was applied
+async fn has_legacy_schema(conn: &tokio_postgres::Client) -> Result<bool> {
+	Ok(conn
+		.query_one(
+			"SELECT EXISTS (
+				SELECT 1 FROM information_schema.tables
+				WHERE table_schema = current_schema() AND table_name = 'transaction_accounts'
+			)",
+			&[],
+		)
+		.await?
+		.get(0))
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::{config::load_config, database::create_database_pool};
+
+	/// Requires a database configured in `Config.toml` and permission to create databases
+	#[tokio::test]
+	async fn test_legacy_schema_is_rejected() {
+		let mut config = load_config("Config.toml").unwrap().database;
+		let db_pool = create_database_pool(&config).await.unwrap();
+		let conn = db_pool.get().await.unwrap();
+		for statement in [
+			"DROP DATABASE IF EXISTS solder_legacy_test",
+			"CREATE DATABASE solder_legacy_test",
+		] {
+			conn.batch_execute(statement).await.unwrap();
+		}
+
+		config.db_name = "solder_legacy_test".to_string();
+		let (client, connection) = tokio_postgres::connect(
+			&format!(
+				"host={} port={} user={} password={} dbname={}",
+				config.host, config.port, config.user, config.password, config.db_name
+			),
+			tokio_postgres::NoTls,
+		)
+		.await
+		.unwrap();
+		tokio::spawn(connection);
+		client
+			.batch_execute(
+				"CREATE TABLE transaction_accounts (
+					blockhash TEXT NOT NULL,
+					slot BIGINT NOT NULL,
+					block_time BIGINT NOT NULL,
+					signature TEXT NOT NULL,
+					account TEXT NOT NULL,
+					PRIMARY KEY (blockhash, signature, account)
+				)",
+			)
+			.await
+			.unwrap();
+		drop(client);
+
+		let result = create_database_pool(&config).await;
+		assert!(matches!(result, Err(AppError::LegacySchema)));
+
+		drop(result);
+		conn.batch_execute("DROP DATABASE solder_legacy_test WITH (FORCE)")
+			.await
+			.unwrap();
+	}
+
+	#[test]
+	fn test_migration_versions_are_contiguous() {
+		for (i, migration) in MIGRATIONS.iter().enumerate() {
+			assert_eq!(migration.version, i as i32 + 1, "{}", migration.name);
+		}
+	}
+}
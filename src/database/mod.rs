@@ -1,3 +1,5 @@
 pub mod conn_pool;
+pub mod migrations;
 
 pub use conn_pool::{DatabasePool, create_database_pool};
+pub use migrations::run_migrations;
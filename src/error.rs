@@ -14,6 +14,15 @@ pub enum AppError {
     #[error("Block not found")]
     BlockNotFound,
 
+    #[error("Slot {0} was skipped")]
+    SlotSkipped(u64),
+
+    #[error("Block not available for slot {0}")]
+    BlockNotAvailable(u64),
+
+    #[error("Rate limited: {0}")]
+    RateLimited(String),
+
     #[error("Could not process block data")]
     BlockProcessingError,
 
@@ -24,7 +33,7 @@ pub enum AppError {
     SendChannelError,
 
     #[error("Solana client error: {0}")]
-    SolanaClientError(#[from] solana_client::client_error::ClientError),
+    SolanaClientError(Box<solana_client::client_error::ClientError>),
 
     #[error("Database error: {0}")]
     DatabaseError(#[from] tokio_postgres::Error),
@@ -35,8 +44,11 @@ pub enum AppError {
     #[error("API error: {0}")]
     AxumError(#[from] axum::Error),
 
+    #[error("Geyser error: {0}")]
+    GeyserError(String),
+
     #[error("Pubsub client error: {0}")]
-    PubsubClientError(#[from] solana_client::pubsub_client::PubsubClientError),
+    PubsubClientError(Box<solana_client::pubsub_client::PubsubClientError>),
 
     #[error("JSON serialization error: {0}")]
     SerdeError(#[from] serde_json::Error),
@@ -77,11 +89,37 @@ pub enum AppError {
     #[error("Config deserialization error: {0}")]
     ConfigDeserializationError(#[from] toml::de::Error),
 
+    #[error("Could not decode instruction: {0}")]
+    InstructionDecodeError(String),
+
     #[error("Invalid date format")]
     InvalidDateFormat(String),
 
     #[error("Invalid request")]
     InvalidRequest,
+
+    #[error("Database schema version {database} is newer than the latest version {binary} supported by this binary")]
+    SchemaVersionAhead { database: i32, binary: i32 },
+
+    #[error("Database schema version {database} is behind the required version {binary}")]
+    SchemaVersionBehind { database: i32, binary: i32 },
+
+    #[error("Database has a transaction_accounts table created before versioned migrations, recreate the database")]
+    LegacySchema,
+}
+
+// The client errors are boxed to keep `AppError`, and every `Result` returned, small
+
+impl From<solana_client::client_error::ClientError> for AppError {
+    fn from(error: solana_client::client_error::ClientError) -> Self {
+        AppError::SolanaClientError(Box::new(error))
+    }
+}
+
+impl From<solana_client::pubsub_client::PubsubClientError> for AppError {
+    fn from(error: solana_client::pubsub_client::PubsubClientError) -> Self {
+        AppError::PubsubClientError(Box::new(error))
+    }
 }
 
 pub type Result<T> = std::result::Result<T, AppError>;
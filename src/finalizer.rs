@@ -0,0 +1,344 @@
+use crate::{
+	backfill::record_gaps, client::endpoints::EndpointPool, config::FinalizerConfig,
+	database::DatabasePool, error::Result, models::hash_bytes,
+};
+use solana_client::rpc_config::RpcBlockConfig;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_transaction_status::TransactionDetails;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::time::{interval, Duration};
+
+/// Most slot leaders the RPC node returns from one `getSlotLeaders` call
+const SLOT_LEADERS_LIMIT: u64 = 5000;
+
+/// Blocks are stored at `confirmed` commitment and may still belong to a fork that the cluster
+/// abandons. The finalizer periodically compares stored `confirmed` blocks at or below the
+/// finalized slot with the finalized blocks of the cluster by slot and blockhash, blocks that
+/// were finalized are marked as such and the remaining ones are deleted together with their
+/// transactions. A slot finalized with a different block than the stored one is recorded as
+/// missing so that the backfill task retrieves the finalized block. Finalized blockhashes are
+/// mostly taken from the `previous_blockhash` of the stored block above that was found finalized,
+/// a block header is only requested where that link is missing. Finalized blocks get the
+/// scheduled leader of their slot from `getSlotLeaders`, the leader stays `NULL` if the leader
+/// schedule of the slot's epoch is no longer available.
+pub struct Finalizer {
+	config: FinalizerConfig,
+	endpoints: Arc<EndpointPool>,
+	db_pool: DatabasePool,
+}
+
+impl Finalizer {
+	pub fn new(
+		config: FinalizerConfig,
+		endpoints: Arc<EndpointPool>,
+		db_pool: DatabasePool,
+	) -> Self {
+		Self {
+			config,
+			endpoints,
+			db_pool,
+		}
+	}
+
+	/// Runs a pass every `interval_secs` until ctrl+c is received
+	pub async fn run(self) -> Result<()> {
+		let mut ticker = interval(Duration::from_secs(self.config.interval_secs));
+
+		loop {
+			tokio::select! {
+				_ = ticker.tick() => {
+					if let Err(e) = self.finalize().await {
+						log::error!("[FINALIZER] Pass failed: {}", e);
+					}
+				}
+				_ = tokio::signal::ctrl_c() => {
+					log::info!("[FINALIZER] Received Ctrl+C signal. Stopping finalizer...");
+					break;
+				}
+			}
+		}
+
+		Ok(())
+	}
+
+	async fn finalize(&self) -> Result<()> {
+		let finalized_slot = self
+			.endpoints
+			.with_client(|client| async move {
+				client
+					.get_slot_with_commitment(CommitmentConfig::finalized())
+					.await
+			})
+			.await?;
+
+		let stored = self.confirmed_blocks(finalized_slot).await?;
+		let (Some(first), Some(last)) = (stored.first(), stored.last()) else {
+			return Ok(());
+		};
+		let (start, end) = (first.slot, last.slot);
+
+		let finalized_slots = self
+			.endpoints
+			.with_client(|client| async move {
+				client
+					.get_blocks_with_commitment(start, Some(end), CommitmentConfig::finalized())
+					.await
+			})
+			.await?;
+		let finalized = self.finalized_blockhashes(&stored, &finalized_slots).await;
+		let finalized_slots: HashSet<u64> = finalized_slots.into_iter().collect();
+		let (finalize, orphaned) = partition_finalized(&stored, &finalized_slots, &finalized);
+		let replaced: Vec<u64> = orphaned
+			.iter()
+			.map(|slot| *slot as u64)
+			.filter(|slot| finalized_slots.contains(slot))
+			.collect();
+
+		log::info!(
+			"[FINALIZER] Checked slots {}-{}: {} finalized, {} orphaned, {} left for the next pass",
+			start,
+			end,
+			finalize.len(),
+			orphaned.len(),
+			stored.len() - finalize.len() - orphaned.len()
+		);
+
+		let conn = self.db_pool.get().await?;
+		if let (Some(&first), Some(&last)) = (finalize.first(), finalize.last()) {
+			let leaders = match self.slot_leaders(first as u64, last as u64).await {
+				Ok(leaders) => leaders,
+				Err(e) => {
+					log::warn!(
+						"[FINALIZER] Could not get the leaders of slots {}-{}: {}",
+						first,
+						last,
+						e
+					);
+					HashMap::new()
+				}
+			};
+			let finalize_leaders: Vec<Option<Vec<u8>>> = finalize
+				.iter()
+				.map(|slot| leaders.get(&(*slot as u64)).cloned())
+				.collect();
+			conn.execute(
+				"UPDATE blocks SET commitment = 'finalized', leader = l.leader
+				FROM UNNEST($1::BIGINT[], $2::BYTEA[]) AS l(slot, leader)
+				WHERE blocks.slot = l.slot",
+				&[&finalize, &finalize_leaders],
+			)
+			.await?;
+		}
+		if !orphaned.is_empty() {
+			for slot in &orphaned {
+				log::warn!("[FINALIZER] Deleting block {} of an abandoned fork", slot);
+			}
+			conn.execute("DELETE FROM blocks WHERE slot = ANY($1)", &[&orphaned])
+				.await?;
+		}
+		record_gaps(&self.db_pool, &replaced, "missing").await?;
+
+		Ok(())
+	}
+
+	/// Blockhashes of the finalized blocks at the stored slots. Finalized slots are walked from the
+	/// top, a stored block whose blockhash matches links its `previous_blockhash` to the finalized
+	/// slot below. The header of a block is only requested when no such link exists, slots whose
+	/// header could not be retrieved are left out and checked again by the next pass.
+	async fn finalized_blockhashes(
+		&self,
+		stored: &[StoredBlock],
+		finalized_slots: &[u64],
+	) -> HashMap<u64, Vec<u8>> {
+		let stored: HashMap<u64, &StoredBlock> =
+			stored.iter().map(|block| (block.slot, block)).collect();
+		let mut finalized = HashMap::new();
+
+		for (i, slot) in finalized_slots.iter().enumerate().rev() {
+			let Some(block) = stored.get(slot) else {
+				continue;
+			};
+			let Some(blockhash) = &block.blockhash else {
+				continue;
+			};
+			let parent_slot = i.checked_sub(1).map(|i| finalized_slots[i]);
+
+			if !finalized.contains_key(slot) {
+				match self.finalized_header(*slot).await {
+					Ok((header_blockhash, previous_blockhash)) => {
+						finalized.insert(*slot, header_blockhash);
+						if let Some(parent_slot) = parent_slot {
+							finalized.insert(parent_slot, previous_blockhash);
+						}
+					}
+					Err(e) => {
+						log::warn!(
+							"[FINALIZER] Could not get the finalized block {}: {}",
+							slot,
+							e
+						);
+						continue;
+					}
+				}
+			}
+
+			if let (Some(parent_slot), Some(previous_blockhash)) =
+				(parent_slot, &block.previous_blockhash)
+			{
+				if finalized.get(slot) == Some(blockhash) {
+					finalized
+						.entry(parent_slot)
+						.or_insert_with(|| previous_blockhash.clone());
+				}
+			}
+		}
+
+		finalized
+	}
+
+	/// Blockhash and previous blockhash of the finalized block at `slot`, only the block header is
+	/// requested
+	async fn finalized_header(&self, slot: u64) -> Result<(Vec<u8>, Vec<u8>)> {
+		let block = self
+			.endpoints
+			.with_client(|client| async move {
+				client
+					.get_block_with_config(
+						slot,
+						RpcBlockConfig {
+							encoding: None,
+							transaction_details: Some(TransactionDetails::None),
+							rewards: Some(false),
+							commitment: Some(CommitmentConfig::finalized()),
+							max_supported_transaction_version: Some(0),
+						},
+					)
+					.await
+			})
+			.await?;
+
+		Ok((
+			hash_bytes(&block.blockhash)?,
+			hash_bytes(&block.previous_blockhash)?,
+		))
+	}
+
+	/// Scheduled leaders of the slots from `start` to `end`, requested in chunks of
+	/// `SLOT_LEADERS_LIMIT` slots
+	async fn slot_leaders(&self, start: u64, end: u64) -> Result<HashMap<u64, Vec<u8>>> {
+		let mut leaders = HashMap::new();
+		for chunk_start in (start..=end).step_by(SLOT_LEADERS_LIMIT as usize) {
+			let limit = SLOT_LEADERS_LIMIT.min(end - chunk_start + 1);
+			let chunk = self
+				.endpoints
+				.with_client(
+					|client| async move { client.get_slot_leaders(chunk_start, limit).await },
+				)
+				.await?;
+			leaders.extend(
+				chunk
+					.into_iter()
+					.enumerate()
+					.map(|(i, leader)| (chunk_start + i as u64, leader.to_bytes().to_vec())),
+			);
+		}
+
+		Ok(leaders)
+	}
+
+	/// The lowest `batch_size` blocks stored at `confirmed` commitment up to `finalized_slot`
+	async fn confirmed_blocks(&self, finalized_slot: u64) -> Result<Vec<StoredBlock>> {
+		let conn = self.db_pool.get().await?;
+		let rows = conn
+			.query(
+				"SELECT slot, blockhash, previous_blockhash FROM blocks
+				WHERE commitment = 'confirmed' AND slot <= $1
+				ORDER BY slot
+				LIMIT $2",
+				&[&(finalized_slot as i64), &self.config.batch_size.max(1)],
+			)
+			.await?;
+
+		Ok(rows
+			.into_iter()
+			.map(|row| StoredBlock {
+				slot: row.get::<_, i64>("slot") as u64,
+				blockhash: row.get("blockhash"),
+				previous_blockhash: row.get("previous_blockhash"),
+			})
+			.collect())
+	}
+}
+
+/// A block stored at `confirmed` commitment, blocks stored without a header have no hashes
+#[derive(Clone, Debug)]
+pub struct StoredBlock {
+	pub slot: u64,
+	pub blockhash: Option<Vec<u8>>,
+	pub previous_blockhash: Option<Vec<u8>>,
+}
+
+/// Splits stored blocks into the slots whose block the cluster finalized and the slots it did
+/// not, either because the slot was not finalized or because a different block was. Blocks
+/// stored without a header are only compared by slot, blocks at a finalized slot whose finalized
+/// blockhash is not known are in neither.
+pub fn partition_finalized(
+	stored: &[StoredBlock],
+	finalized_slots: &HashSet<u64>,
+	finalized: &HashMap<u64, Vec<u8>>,
+) -> (Vec<i64>, Vec<i64>) {
+	let mut finalize = Vec::new();
+	let mut orphaned = Vec::new();
+
+	for block in stored {
+		if !finalized_slots.contains(&block.slot) {
+			orphaned.push(block.slot as i64);
+			continue;
+		}
+		match (&block.blockhash, finalized.get(&block.slot)) {
+			(None, _) => finalize.push(block.slot as i64),
+			(Some(blockhash), Some(finalized)) if blockhash == finalized => {
+				finalize.push(block.slot as i64)
+			}
+			(Some(_), Some(_)) => orphaned.push(block.slot as i64),
+			(Some(_), None) => {}
+		}
+	}
+
+	(finalize, orphaned)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn stored(slot: u64, blockhash: Option<u8>, previous_blockhash: Option<u8>) -> StoredBlock {
+		StoredBlock {
+			slot,
+			blockhash: blockhash.map(|hash| vec![hash]),
+			previous_blockhash: previous_blockhash.map(|hash| vec![hash]),
+		}
+	}
+
+	#[test]
+	fn test_partition_finalized() {
+		let finalized_slots = HashSet::from([10, 11, 13, 15]);
+		let finalized = HashMap::from([(10, vec![1]), (13, vec![3])]);
+		let stored = [
+			stored(10, Some(1), None),
+			stored(11, None, None),
+			stored(12, Some(4), None),
+			stored(13, Some(5), None),
+			stored(14, None, None),
+			stored(15, Some(6), None),
+		];
+
+		let (finalize, orphaned) = partition_finalized(&stored, &finalized_slots, &finalized);
+
+		// Slot 13 was finalized with a block of another fork, slots 11 and 14 have no header and
+		// the finalized blockhash of slot 15 is not known
+		assert_eq!(finalize, vec![10, 11]);
+		assert_eq!(orphaned, vec![12, 13, 14]);
+	}
+}
@@ -1,7 +1,11 @@
 pub mod api;
+pub mod app;
+pub mod backfill;
+pub mod channel;
 pub mod config;
 pub mod database;
 pub mod error;
+pub mod finalizer;
 pub mod models;
 pub mod pool;
 pub mod processor;
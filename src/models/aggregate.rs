@@ -1,34 +1,150 @@
 use serde::Serialize;
 
-/// Used as both the storage type and response type
+/// Response type for an account's transactions, built from `blocks`, `transactions` and
+/// `transaction_accounts`
 #[derive(Serialize, Clone, Debug)]
 pub struct Aggregate {
-	pub blockhash: String,
+	pub blockhash: Option<String>,
 	pub slot: i64,
-	pub block_time: i64,
+	pub block_time: Option<i64>,
 	pub signature: String,
 	pub account: String,
+	pub is_signer: bool,
+	pub is_writable: bool,
+	pub from_lookup_table: bool,
+	pub pre_balance: Option<i64>,
+	pub post_balance: Option<i64>,
+	pub lamport_delta: Option<i64>,
 }
 
 #[derive(Serialize, Clone, Debug)]
 pub struct BlockResponse {
-	pub blockhash: String,
+	pub blockhash: Option<String>,
 	pub slot: i64,
-	pub block_time: i64,
+	pub block_time: Option<i64>,
+	pub commitment: String,
+	pub leader: Option<String>,
+	pub parent_slot: Option<i64>,
+	pub block_height: Option<i64>,
+	pub previous_blockhash: Option<String>,
+	pub parent: Option<BlockLink>,
+	pub children: Vec<BlockLink>,
 	pub transactions: Vec<BlockTransactions>,
 }
 
+/// Navigation link to a stored block, a block has more than one child if a fork was stored
+#[derive(Serialize, Clone, Debug)]
+pub struct BlockLink {
+	pub slot: i64,
+	pub blockhash: String,
+	pub href: String,
+}
+
+impl BlockLink {
+	pub fn new(slot: i64, blockhash: String) -> Self {
+		Self {
+			slot,
+			href: format!("/api/block/{}", blockhash),
+			blockhash,
+		}
+	}
+}
+
 #[derive(Serialize, Clone, Debug)]
 pub struct BlockTransactions {
 	pub signature: String,
+	pub success: Option<bool>,
+	pub err: Option<String>,
+	pub fee: Option<i64>,
+	pub compute_units_consumed: Option<i64>,
+	pub log_message_count: Option<i32>,
 	pub accounts: Vec<String>,
 }
 
 #[derive(Serialize, Clone, Debug)]
 pub struct TransactionResponse {
-	pub blockhash: String,
+	pub blockhash: Option<String>,
 	pub slot: i64,
-	pub block_time: i64,
+	pub block_time: Option<i64>,
+	pub commitment: String,
 	pub signature: String,
+	pub success: Option<bool>,
+	pub err: Option<String>,
+	pub fee: Option<i64>,
+	pub compute_units_consumed: Option<i64>,
+	pub log_message_count: Option<i32>,
 	pub accounts: Vec<String>,
 }
+
+/// Response type for an account's token transfers, amounts are raw token amounts as strings since
+/// they may exceed what JSON numbers can represent exactly
+#[derive(Serialize, Clone, Debug)]
+pub struct TokenTransfer {
+	pub blockhash: Option<String>,
+	pub slot: i64,
+	pub block_time: Option<i64>,
+	pub signature: String,
+	pub success: Option<bool>,
+	pub account: String,
+	pub mint: String,
+	pub owner: Option<String>,
+	pub decimals: i16,
+	pub pre_amount: String,
+	pub post_amount: String,
+	pub delta: String,
+}
+
+/// Response type for an account's native SOL balance history over a date range. `start_balance`
+/// is the balance before the first transaction in the range and `end_balance` the balance after
+/// the last, all amounts are in lamports.
+#[derive(Serialize, Clone, Debug)]
+pub struct BalanceHistory {
+	pub account: String,
+	pub start_balance: Option<i64>,
+	pub end_balance: Option<i64>,
+	pub net_change: i64,
+	pub history: Vec<BalancePoint>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct BalancePoint {
+	pub slot: i64,
+	pub block_time: Option<i64>,
+	pub signature: String,
+	pub pre_balance: i64,
+	pub post_balance: i64,
+	pub lamport_delta: i64,
+}
+
+/// Response type for a program's invocations, `inner_index` is `None` for top level instructions
+#[derive(Serialize, Clone, Debug)]
+pub struct ProgramInvocation {
+	pub blockhash: Option<String>,
+	pub slot: i64,
+	pub block_time: Option<i64>,
+	pub signature: String,
+	pub success: Option<bool>,
+	pub program_id: String,
+	pub instruction_index: i16,
+	pub inner_index: Option<i16>,
+	pub stack_height: Option<i32>,
+	pub decoded: Option<serde_json::Value>,
+}
+
+/// Response type for the blocks produced by a validator identity, reward amounts are the
+/// lamports credited to the identity in each block
+#[derive(Serialize, Clone, Debug)]
+pub struct ValidatorBlocks {
+	pub identity: String,
+	pub block_count: usize,
+	pub total_rewards: i64,
+	pub blocks: Vec<ValidatorBlock>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ValidatorBlock {
+	pub blockhash: Option<String>,
+	pub slot: i64,
+	pub block_time: Option<i64>,
+	pub reward_lamports: i64,
+}
@@ -0,0 +1,115 @@
+use serde::Serialize;
+use serde_json::Value;
+
+/// Output of block processing and the storage type for the `blocks`, `transactions` and
+/// `transaction_accounts` tables. Keys are kept as base58 strings and converted to bytes on
+/// storage. `parent_slot` and `previous_blockhash` link the block to its parent. Blocks built from a watched transaction
+/// have no header, their `blockhash`, `parent_slot` and `previous_blockhash` are `None`.
+/// `block_time` is `None` if the RPC node did not return it.
+#[derive(Clone, Debug)]
+pub struct ProcessedBlock {
+	pub slot: i64,
+	pub blockhash: Option<String>,
+	pub block_time: Option<i64>,
+	pub parent_slot: Option<i64>,
+	pub block_height: Option<i64>,
+	pub previous_blockhash: Option<String>,
+	pub rewards: Vec<BlockReward>,
+	pub transactions: Vec<ProcessedTransaction>,
+}
+
+/// Reward credited in a block, `reward_type` is one of `fee`, `rent`, `staking` or `voting` and
+/// `commission` is only set for staking and voting rewards
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockReward {
+	pub pubkey: String,
+	pub lamports: i64,
+	pub post_balance: i64,
+	pub reward_type: Option<String>,
+	pub commission: Option<i16>,
+}
+
+/// `err` is `None` for successful transactions, `fee` is `None` if the RPC node did not return the
+/// transaction's meta, in which case its status is unknown. `compute_units_consumed` and
+/// `log_message_count` are `None` if the RPC node did not return them. `token_balance_changes`
+/// holds the token accounts whose balance changed in the transaction. `lamport_balances` lines up
+/// with `accounts` and is empty if the RPC node did not return the transaction's meta.
+/// `instructions` holds top level and inner instructions in execution order.
+#[derive(Clone, Debug)]
+pub struct ProcessedTransaction {
+	pub signature: String,
+	pub accounts: Vec<AccountKey>,
+	pub err: Option<String>,
+	pub fee: Option<i64>,
+	pub compute_units_consumed: Option<i64>,
+	pub log_message_count: Option<i32>,
+	pub token_balance_changes: Vec<TokenBalanceChange>,
+	pub lamport_balances: Vec<LamportBalance>,
+	pub instructions: Vec<ProcessedInstruction>,
+}
+
+/// An instruction invoked by a transaction. `instruction_index` is the index of the top level
+/// instruction, inner instructions also carry their position among the inner instructions it
+/// invoked. Instructions the RPC node returned already parsed have no `accounts` or `data`.
+/// `decoded` is set if a decoder is registered for the program or the RPC node parsed it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProcessedInstruction {
+	pub instruction_index: i16,
+	pub inner_index: Option<i16>,
+	pub stack_height: Option<i32>,
+	pub program_id: String,
+	pub accounts: Vec<String>,
+	pub data: Vec<u8>,
+	pub decoded: Option<DecodedInstruction>,
+}
+
+/// Structured form of an instruction, `info` holds the fields of the instruction type
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct DecodedInstruction {
+	pub program: String,
+	#[serde(rename = "type")]
+	pub instruction_type: String,
+	pub info: Value,
+}
+
+impl DecodedInstruction {
+	pub fn new(program: &str, instruction_type: &str, info: Value) -> Self {
+		Self {
+			program: program.to_string(),
+			instruction_type: instruction_type.to_string(),
+			info,
+		}
+	}
+}
+
+/// Native SOL balance of an account before and after a transaction, in lamports
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LamportBalance {
+	pub pre: i64,
+	pub post: i64,
+}
+
+/// An account referenced by a transaction and whether it signed the transaction or was
+/// writable in the message. `from_lookup_table` is set for accounts of v0 transactions loaded
+/// through an address lookup table rather than listed in the message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AccountKey {
+	pub pubkey: String,
+	pub signer: bool,
+	pub writable: bool,
+	pub from_lookup_table: bool,
+}
+
+/// Change in a token account's balance within a transaction, amounts are raw amounts before
+/// applying `decimals`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TokenBalanceChange {
+	pub account_index: i16,
+	pub account: String,
+	pub mint: String,
+	pub owner: Option<String>,
+	pub decimals: i16,
+	pub pre_amount: u64,
+	pub post_amount: u64,
+	pub delta: i128,
+}
@@ -1,16 +1,39 @@
 pub mod aggregate;
+pub mod block;
 
 pub use aggregate::*;
+pub use block::*;
 
 use crate::error::{AppError, Result};
+use solana_sdk::{hash::Hash, pubkey::Pubkey, signature::Signature};
+use std::str::FromStr;
 use solana_transaction_status::{
-	EncodedTransaction, EncodedTransactionWithStatusMeta, UiMessage, UiTransaction,
+	parse_accounts::ParsedAccountSource, EncodedTransaction, EncodedTransactionWithStatusMeta,
+	UiMessage, UiTransaction,
 };
 
-/// Unused type alias
+/// Block notification of `blockSubscribe`
 pub type BlockUpdate =
 	solana_client::rpc_response::Response<solana_client::rpc_response::RpcBlockUpdate>;
 
+/// Base58 encoded pubkeys, signatures and blockhashes are stored as raw bytes
+pub fn pubkey_bytes(pubkey: &str) -> Result<Vec<u8>> {
+	Ok(Pubkey::from_str(pubkey)?.to_bytes().to_vec())
+}
+
+pub fn signature_bytes(signature: &str) -> Result<Vec<u8>> {
+	Ok(Signature::from_str(signature)?.as_ref().to_vec())
+}
+
+pub fn hash_bytes(hash: &str) -> Result<Vec<u8>> {
+	Ok(Hash::from_str(hash)?.to_bytes().to_vec())
+}
+
+/// Encodes stored bytes back to their base58 representation
+pub fn to_base58(bytes: &[u8]) -> String {
+	bs58::encode(bytes).into_string()
+}
+
 /// For decoding `Encoded` types in `solana_transaction_status`
 pub trait TryDecode<T>: Sized {
 	type Error;
@@ -35,12 +58,79 @@ impl TryDecode<EncodedTransactionWithStatusMeta> for UiTransaction {
 	}
 }
 
-/// Get accounts from a `UiMessage`
-impl FromMsg<UiMessage> for Vec<String> {
+/// Get accounts and their signer/writable roles from a `UiMessage`. Roles of raw messages are
+/// derived from the message header, signers come first followed by unsigned accounts with the
+/// read-only accounts of each group at the end. Parsed messages carry the roles and source per
+/// account.
+impl FromMsg<UiMessage> for Vec<AccountKey> {
 	fn from_msg(msg: UiMessage) -> Self {
 		match msg {
-			UiMessage::Raw(msg) => msg.account_keys,
-			UiMessage::Parsed(msg) => msg.account_keys.into_iter().map(|x| x.pubkey).collect(),
+			UiMessage::Raw(msg) => {
+				let header = msg.header;
+				let num_keys = msg.account_keys.len();
+				let num_signers = header.num_required_signatures as usize;
+				let num_writable_signers =
+					num_signers.saturating_sub(header.num_readonly_signed_accounts as usize);
+				let num_writable_unsigned =
+					num_keys.saturating_sub(header.num_readonly_unsigned_accounts as usize);
+
+				msg.account_keys
+					.into_iter()
+					.enumerate()
+					.map(|(i, pubkey)| AccountKey {
+						pubkey,
+						signer: i < num_signers,
+						writable: if i < num_signers {
+							i < num_writable_signers
+						} else {
+							i < num_writable_unsigned
+						},
+						from_lookup_table: false,
+					})
+					.collect()
+			}
+			UiMessage::Parsed(msg) => msg
+				.account_keys
+				.into_iter()
+				.map(|x| AccountKey {
+					pubkey: x.pubkey,
+					signer: x.signer,
+					writable: x.writable,
+					from_lookup_table: x.source == Some(ParsedAccountSource::LookupTable),
+				})
+				.collect(),
 		}
 	}
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use solana_sdk::message::MessageHeader;
+	use solana_transaction_status::UiRawMessage;
+
+	#[test]
+	fn test_account_roles_from_header() {
+		let msg = UiMessage::Raw(UiRawMessage {
+			header: MessageHeader {
+				num_required_signatures: 2,
+				num_readonly_signed_accounts: 1,
+				num_readonly_unsigned_accounts: 1,
+			},
+			account_keys: vec!["a", "b", "c", "d"].into_iter().map(String::from).collect(),
+			recent_blockhash: String::new(),
+			instructions: Vec::new(),
+			address_table_lookups: None,
+		});
+
+		let roles: Vec<(bool, bool)> = <Vec<AccountKey>>::from_msg(msg)
+			.into_iter()
+			.map(|key| (key.signer, key.writable))
+			.collect();
+
+		assert_eq!(
+			roles,
+			vec![(true, true), (true, false), (false, true), (false, false)]
+		);
+	}
+}
@@ -28,6 +28,6 @@ impl ThreadPool {
 	{
 		let runtime = Arc::clone(&self.runtime);
 
-		runtime.spawn(async move { f.await })
+		runtime.spawn(f)
 	}
 }
@@ -0,0 +1,56 @@
+use super::{account, InstructionDecoder};
+use crate::{
+	error::{AppError, Result},
+	models::{DecodedInstruction, ProcessedInstruction},
+};
+use serde_json::{json, Value};
+
+/// Decodes Associated Token Account instructions, empty data is the original `create`
+pub struct AssociatedTokenDecoder;
+
+impl InstructionDecoder for AssociatedTokenDecoder {
+	fn decode(&self, instruction: &ProcessedInstruction) -> Result<DecodedInstruction> {
+		let ix = instruction;
+
+		let (instruction_type, info) = match ix.data.first() {
+			None | Some(0) => ("create", create_info(ix)?),
+			Some(1) => ("createIdempotent", create_info(ix)?),
+			Some(2) => (
+				"recoverNested",
+				json!({
+					"nestedSource": account(ix, 0)?,
+					"nestedMint": account(ix, 1)?,
+					"destination": account(ix, 2)?,
+					"nestedOwner": account(ix, 3)?,
+					"ownerMint": account(ix, 4)?,
+					"wallet": account(ix, 5)?,
+					"tokenProgram": account(ix, 6)?,
+				}),
+			),
+			Some(tag) => {
+				return Err(AppError::InstructionDecodeError(format!(
+					"unknown associated token instruction {}",
+					tag
+				)))
+			}
+		};
+
+		Ok(DecodedInstruction::new(
+			"spl-associated-token-account",
+			instruction_type,
+			info,
+		))
+	}
+}
+
+/// Accounts of `create` and `createIdempotent`
+fn create_info(ix: &ProcessedInstruction) -> Result<Value> {
+	Ok(json!({
+		"source": account(ix, 0)?,
+		"account": account(ix, 1)?,
+		"wallet": account(ix, 2)?,
+		"mint": account(ix, 3)?,
+		"systemProgram": account(ix, 4)?,
+		"tokenProgram": account(ix, 5)?,
+	}))
+}
@@ -0,0 +1,44 @@
+use super::{DataReader, InstructionDecoder};
+use crate::{
+	error::{AppError, Result},
+	models::{DecodedInstruction, ProcessedInstruction},
+};
+use serde_json::json;
+
+/// Decodes Compute Budget instructions, the instruction is tagged by its first byte
+pub struct ComputeBudgetDecoder;
+
+impl InstructionDecoder for ComputeBudgetDecoder {
+	fn decode(&self, instruction: &ProcessedInstruction) -> Result<DecodedInstruction> {
+		let mut data = DataReader::new(&instruction.data);
+
+		let (instruction_type, info) = match data.u8()? {
+			0 => (
+				"requestUnits",
+				json!({ "units": data.u32()?, "additionalFee": data.u32()? }),
+			),
+			1 => ("requestHeapFrame", json!({ "bytes": data.u32()? })),
+			2 => ("setComputeUnitLimit", json!({ "units": data.u32()? })),
+			3 => (
+				"setComputeUnitPrice",
+				json!({ "microLamports": data.u64()? }),
+			),
+			4 => (
+				"setLoadedAccountsDataSizeLimit",
+				json!({ "bytes": data.u32()? }),
+			),
+			tag => {
+				return Err(AppError::InstructionDecodeError(format!(
+					"unknown compute budget instruction {}",
+					tag
+				)))
+			}
+		};
+
+		Ok(DecodedInstruction::new(
+			"compute-budget",
+			instruction_type,
+			info,
+		))
+	}
+}
@@ -0,0 +1,22 @@
+use super::InstructionDecoder;
+use crate::{
+	error::{AppError, Result},
+	models::{DecodedInstruction, ProcessedInstruction},
+};
+use serde_json::json;
+
+/// Decodes Memo instructions, the data is the utf-8 memo and the accounts its signers
+pub struct MemoDecoder;
+
+impl InstructionDecoder for MemoDecoder {
+	fn decode(&self, instruction: &ProcessedInstruction) -> Result<DecodedInstruction> {
+		let memo = std::str::from_utf8(&instruction.data)
+			.map_err(|e| AppError::InstructionDecodeError(e.to_string()))?;
+
+		Ok(DecodedInstruction::new(
+			"spl-memo",
+			"memo",
+			json!({ "memo": memo, "signers": instruction.accounts }),
+		))
+	}
+}
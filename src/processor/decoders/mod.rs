@@ -0,0 +1,98 @@
+pub mod associated_token;
+pub mod compute_budget;
+pub mod memo;
+pub mod reader;
+pub mod system;
+pub mod token;
+
+pub use associated_token::AssociatedTokenDecoder;
+pub use compute_budget::ComputeBudgetDecoder;
+pub use memo::MemoDecoder;
+pub use reader::DataReader;
+pub use system::SystemDecoder;
+pub use token::TokenDecoder;
+
+use crate::{
+	error::{AppError, Result},
+	models::{DecodedInstruction, ProcessedInstruction},
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+pub const SYSTEM_PROGRAM_ID: &str = "11111111111111111111111111111111";
+pub const TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+pub const TOKEN_2022_PROGRAM_ID: &str = "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb";
+pub const ASSOCIATED_TOKEN_PROGRAM_ID: &str = "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL";
+pub const COMPUTE_BUDGET_PROGRAM_ID: &str = "ComputeBudget111111111111111111111111111111";
+pub const MEMO_PROGRAM_ID: &str = "MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr";
+pub const MEMO_V1_PROGRAM_ID: &str = "Memo1UhkJRfHyvLMcVucJwxXeuD728EqVDDwQDxFMNo";
+
+/// Trait to decode the raw data and accounts of a program's instructions into structured JSON.
+/// Implement it and add it to a `DecoderRegistry` to decode instructions of other programs.
+pub trait InstructionDecoder: Send + Sync {
+	fn decode(&self, instruction: &ProcessedInstruction) -> Result<DecodedInstruction>;
+}
+
+/// Decoders keyed by the base58 program id they decode instructions for
+#[derive(Clone, Default)]
+pub struct DecoderRegistry {
+	decoders: HashMap<String, Arc<dyn InstructionDecoder>>,
+}
+
+impl DecoderRegistry {
+	/// Creates an empty registry, use `with_builtin` for the decoders of well known programs
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Registry with decoders for the System, SPL Token, Token-2022, Associated Token Account,
+	/// Compute Budget and Memo programs
+	pub fn with_builtin() -> Self {
+		let mut registry = Self::new();
+		registry
+			.register(SYSTEM_PROGRAM_ID, SystemDecoder)
+			.register(TOKEN_PROGRAM_ID, TokenDecoder::new("spl-token"))
+			.register(TOKEN_2022_PROGRAM_ID, TokenDecoder::new("spl-token-2022"))
+			.register(ASSOCIATED_TOKEN_PROGRAM_ID, AssociatedTokenDecoder)
+			.register(COMPUTE_BUDGET_PROGRAM_ID, ComputeBudgetDecoder)
+			.register(MEMO_PROGRAM_ID, MemoDecoder)
+			.register(MEMO_V1_PROGRAM_ID, MemoDecoder);
+		registry
+	}
+
+	/// Registers a decoder for a program, replacing any decoder already registered for it
+	pub fn register<D>(&mut self, program_id: &str, decoder: D) -> &mut Self
+	where
+		D: InstructionDecoder + 'static,
+	{
+		self.decoders
+			.insert(program_id.to_string(), Arc::new(decoder));
+		self
+	}
+
+	/// Decodes an instruction with the decoder registered for its program. Returns `None` if no
+	/// decoder is registered or the instruction could not be decoded.
+	pub fn decode(&self, instruction: &ProcessedInstruction) -> Option<DecodedInstruction> {
+		let decoder = self.decoders.get(&instruction.program_id)?;
+		match decoder.decode(instruction) {
+			Ok(decoded) => Some(decoded),
+			Err(e) => {
+				log::debug!(
+					"Could not decode instruction of {}: {}",
+					instruction.program_id,
+					e
+				);
+				None
+			}
+		}
+	}
+}
+
+/// Account of an instruction by position, errors if the instruction has fewer accounts
+pub(crate) fn account(instruction: &ProcessedInstruction, index: usize) -> Result<String> {
+	instruction
+		.accounts
+		.get(index)
+		.cloned()
+		.ok_or_else(|| AppError::InstructionDecodeError(format!("missing account {}", index)))
+}
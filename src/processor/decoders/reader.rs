@@ -0,0 +1,59 @@
+use crate::error::{AppError, Result};
+
+/// Cursor over instruction data reading the little endian integers and pubkeys that native and
+/// SPL programs encode their instructions with
+pub struct DataReader<'a> {
+	data: &'a [u8],
+}
+
+impl<'a> DataReader<'a> {
+	pub fn new(data: &'a [u8]) -> Self {
+		Self { data }
+	}
+
+	fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+		if self.data.len() < len {
+			return Err(AppError::InstructionDecodeError(format!(
+				"expected {} bytes, {} remaining",
+				len,
+				self.data.len()
+			)));
+		}
+		let (head, tail) = self.data.split_at(len);
+		self.data = tail;
+		Ok(head)
+	}
+
+	pub fn u8(&mut self) -> Result<u8> {
+		Ok(self.take(1)?[0])
+	}
+
+	pub fn u32(&mut self) -> Result<u32> {
+		Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+	}
+
+	pub fn u64(&mut self) -> Result<u64> {
+		Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+	}
+
+	/// 32 byte pubkey returned as base58
+	pub fn pubkey(&mut self) -> Result<String> {
+		Ok(bs58::encode(self.take(32)?).into_string())
+	}
+
+	/// Pubkey preceded by a one byte tag, as used by SPL Token for optional authorities
+	pub fn option_pubkey(&mut self) -> Result<Option<String>> {
+		match self.u8()? {
+			0 => Ok(None),
+			_ => self.pubkey().map(Some),
+		}
+	}
+
+	/// Bincode string, a `u64` length followed by utf-8 bytes
+	pub fn string(&mut self) -> Result<String> {
+		let len = self.u64()? as usize;
+		let bytes = self.take(len)?;
+		String::from_utf8(bytes.to_vec())
+			.map_err(|e| AppError::InstructionDecodeError(e.to_string()))
+	}
+}
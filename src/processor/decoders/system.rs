@@ -0,0 +1,150 @@
+use super::{account, DataReader, InstructionDecoder};
+use crate::{
+	error::{AppError, Result},
+	models::{DecodedInstruction, ProcessedInstruction},
+};
+use serde_json::json;
+
+/// Decodes System program instructions, the instruction is a bincode enum with a `u32` tag
+pub struct SystemDecoder;
+
+impl InstructionDecoder for SystemDecoder {
+	fn decode(&self, instruction: &ProcessedInstruction) -> Result<DecodedInstruction> {
+		let ix = instruction;
+		let mut data = DataReader::new(&ix.data);
+
+		let (instruction_type, info) = match data.u32()? {
+			0 => (
+				"createAccount",
+				json!({
+					"source": account(ix, 0)?,
+					"newAccount": account(ix, 1)?,
+					"lamports": data.u64()?,
+					"space": data.u64()?,
+					"owner": data.pubkey()?,
+				}),
+			),
+			1 => (
+				"assign",
+				json!({ "account": account(ix, 0)?, "owner": data.pubkey()? }),
+			),
+			2 => (
+				"transfer",
+				json!({
+					"source": account(ix, 0)?,
+					"destination": account(ix, 1)?,
+					"lamports": data.u64()?,
+				}),
+			),
+			3 => (
+				"createAccountWithSeed",
+				json!({
+					"source": account(ix, 0)?,
+					"newAccount": account(ix, 1)?,
+					"base": data.pubkey()?,
+					"seed": data.string()?,
+					"lamports": data.u64()?,
+					"space": data.u64()?,
+					"owner": data.pubkey()?,
+				}),
+			),
+			4 => (
+				"advanceNonce",
+				json!({ "nonceAccount": account(ix, 0)?, "nonceAuthority": account(ix, 2)? }),
+			),
+			5 => (
+				"withdrawFromNonce",
+				json!({
+					"nonceAccount": account(ix, 0)?,
+					"destination": account(ix, 1)?,
+					"nonceAuthority": account(ix, 4)?,
+					"lamports": data.u64()?,
+				}),
+			),
+			6 => (
+				"initializeNonce",
+				json!({ "nonceAccount": account(ix, 0)?, "nonceAuthority": data.pubkey()? }),
+			),
+			7 => (
+				"authorizeNonce",
+				json!({
+					"nonceAccount": account(ix, 0)?,
+					"nonceAuthority": account(ix, 1)?,
+					"newAuthorized": data.pubkey()?,
+				}),
+			),
+			8 => (
+				"allocate",
+				json!({ "account": account(ix, 0)?, "space": data.u64()? }),
+			),
+			9 => (
+				"allocateWithSeed",
+				json!({
+					"account": account(ix, 0)?,
+					"base": data.pubkey()?,
+					"seed": data.string()?,
+					"space": data.u64()?,
+					"owner": data.pubkey()?,
+				}),
+			),
+			10 => (
+				"assignWithSeed",
+				json!({
+					"account": account(ix, 0)?,
+					"base": data.pubkey()?,
+					"seed": data.string()?,
+					"owner": data.pubkey()?,
+				}),
+			),
+			11 => (
+				"transferWithSeed",
+				json!({
+					"source": account(ix, 0)?,
+					"sourceBase": account(ix, 1)?,
+					"destination": account(ix, 2)?,
+					"lamports": data.u64()?,
+					"sourceSeed": data.string()?,
+					"sourceOwner": data.pubkey()?,
+				}),
+			),
+			12 => ("upgradeNonce", json!({ "nonceAccount": account(ix, 0)? })),
+			tag => {
+				return Err(AppError::InstructionDecodeError(format!(
+					"unknown system instruction {}",
+					tag
+				)))
+			}
+		};
+
+		Ok(DecodedInstruction::new("system", instruction_type, info))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::processor::decoders::SYSTEM_PROGRAM_ID;
+
+	#[test]
+	fn test_decode_transfer() {
+		let mut data = 2u32.to_le_bytes().to_vec();
+		data.extend(1_000_000u64.to_le_bytes());
+		let instruction = ProcessedInstruction {
+			instruction_index: 0,
+			inner_index: None,
+			stack_height: None,
+			program_id: SYSTEM_PROGRAM_ID.to_string(),
+			accounts: vec!["from".to_string(), "to".to_string()],
+			data,
+			decoded: None,
+		};
+
+		let decoded = SystemDecoder.decode(&instruction).unwrap();
+
+		assert_eq!(decoded.instruction_type, "transfer");
+		assert_eq!(
+			decoded.info,
+			json!({ "source": "from", "destination": "to", "lamports": 1_000_000 })
+		);
+	}
+}
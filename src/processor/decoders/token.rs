@@ -0,0 +1,303 @@
+use super::{account, DataReader, InstructionDecoder};
+use crate::{
+	error::{AppError, Result},
+	models::{DecodedInstruction, ProcessedInstruction},
+};
+use serde_json::json;
+
+/// Decodes SPL Token instructions, the instruction is tagged by its first byte. Token-2022 shares
+/// the instructions of SPL Token, its extension instructions are reported by type only as their
+/// layout depends on the extension.
+pub struct TokenDecoder {
+	program: &'static str,
+}
+
+impl TokenDecoder {
+	pub fn new(program: &'static str) -> Self {
+		Self { program }
+	}
+}
+
+/// Token-2022 extension instructions by tag, starting at `EXTENSION_START`
+const EXTENSION_START: u8 = 25;
+const EXTENSIONS: &[&str] = &[
+	"initializeMintCloseAuthority",
+	"transferFeeExtension",
+	"confidentialTransferExtension",
+	"defaultAccountStateExtension",
+	"reallocate",
+	"memoTransferExtension",
+	"createNativeMint",
+	"initializeNonTransferableMint",
+	"interestBearingMintExtension",
+	"cpiGuardExtension",
+	"initializePermanentDelegate",
+	"transferHookExtension",
+	"confidentialTransferFeeExtension",
+	"withdrawExcessLamports",
+	"metadataPointerExtension",
+	"groupPointerExtension",
+	"groupMemberPointerExtension",
+];
+
+/// Authority types of `SetAuthority`
+const AUTHORITY_TYPES: &[&str] = &[
+	"mintTokens",
+	"freezeAccount",
+	"accountOwner",
+	"closeAccount",
+	"transferFeeConfig",
+	"withheldWithdraw",
+	"closeMint",
+	"interestRate",
+	"permanentDelegate",
+	"confidentialTransferMint",
+	"transferHookProgramId",
+	"confidentialTransferFeeConfig",
+	"metadataPointer",
+	"groupPointer",
+	"groupMemberPointer",
+];
+
+impl InstructionDecoder for TokenDecoder {
+	fn decode(&self, instruction: &ProcessedInstruction) -> Result<DecodedInstruction> {
+		let ix = instruction;
+		let mut data = DataReader::new(&ix.data);
+
+		let (instruction_type, info) = match data.u8()? {
+			0 => (
+				"initializeMint",
+				json!({
+					"mint": account(ix, 0)?,
+					"decimals": data.u8()?,
+					"mintAuthority": data.pubkey()?,
+					"freezeAuthority": data.option_pubkey()?,
+				}),
+			),
+			1 => (
+				"initializeAccount",
+				json!({
+					"account": account(ix, 0)?,
+					"mint": account(ix, 1)?,
+					"owner": account(ix, 2)?,
+				}),
+			),
+			2 => (
+				"initializeMultisig",
+				json!({
+					"multisig": account(ix, 0)?,
+					"signers": ix.accounts.iter().skip(2).collect::<Vec<_>>(),
+					"m": data.u8()?,
+				}),
+			),
+			3 => (
+				"transfer",
+				json!({
+					"source": account(ix, 0)?,
+					"destination": account(ix, 1)?,
+					"authority": account(ix, 2)?,
+					"amount": data.u64()?.to_string(),
+				}),
+			),
+			4 => (
+				"approve",
+				json!({
+					"source": account(ix, 0)?,
+					"delegate": account(ix, 1)?,
+					"owner": account(ix, 2)?,
+					"amount": data.u64()?.to_string(),
+				}),
+			),
+			5 => (
+				"revoke",
+				json!({ "source": account(ix, 0)?, "owner": account(ix, 1)? }),
+			),
+			6 => {
+				let authority_type = data.u8()?;
+				(
+					"setAuthority",
+					json!({
+						"account": account(ix, 0)?,
+						"authority": account(ix, 1)?,
+						"authorityType": AUTHORITY_TYPES.get(authority_type as usize),
+						"newAuthority": data.option_pubkey()?,
+					}),
+				)
+			}
+			7 => (
+				"mintTo",
+				json!({
+					"mint": account(ix, 0)?,
+					"account": account(ix, 1)?,
+					"mintAuthority": account(ix, 2)?,
+					"amount": data.u64()?.to_string(),
+				}),
+			),
+			8 => (
+				"burn",
+				json!({
+					"account": account(ix, 0)?,
+					"mint": account(ix, 1)?,
+					"authority": account(ix, 2)?,
+					"amount": data.u64()?.to_string(),
+				}),
+			),
+			9 => (
+				"closeAccount",
+				json!({
+					"account": account(ix, 0)?,
+					"destination": account(ix, 1)?,
+					"owner": account(ix, 2)?,
+				}),
+			),
+			10 => (
+				"freezeAccount",
+				json!({
+					"account": account(ix, 0)?,
+					"mint": account(ix, 1)?,
+					"freezeAuthority": account(ix, 2)?,
+				}),
+			),
+			11 => (
+				"thawAccount",
+				json!({
+					"account": account(ix, 0)?,
+					"mint": account(ix, 1)?,
+					"freezeAuthority": account(ix, 2)?,
+				}),
+			),
+			12 => (
+				"transferChecked",
+				json!({
+					"source": account(ix, 0)?,
+					"mint": account(ix, 1)?,
+					"destination": account(ix, 2)?,
+					"authority": account(ix, 3)?,
+					"amount": data.u64()?.to_string(),
+					"decimals": data.u8()?,
+				}),
+			),
+			13 => (
+				"approveChecked",
+				json!({
+					"source": account(ix, 0)?,
+					"mint": account(ix, 1)?,
+					"delegate": account(ix, 2)?,
+					"owner": account(ix, 3)?,
+					"amount": data.u64()?.to_string(),
+					"decimals": data.u8()?,
+				}),
+			),
+			14 => (
+				"mintToChecked",
+				json!({
+					"mint": account(ix, 0)?,
+					"account": account(ix, 1)?,
+					"mintAuthority": account(ix, 2)?,
+					"amount": data.u64()?.to_string(),
+					"decimals": data.u8()?,
+				}),
+			),
+			15 => (
+				"burnChecked",
+				json!({
+					"account": account(ix, 0)?,
+					"mint": account(ix, 1)?,
+					"authority": account(ix, 2)?,
+					"amount": data.u64()?.to_string(),
+					"decimals": data.u8()?,
+				}),
+			),
+			16 => (
+				"initializeAccount2",
+				json!({
+					"account": account(ix, 0)?,
+					"mint": account(ix, 1)?,
+					"owner": data.pubkey()?,
+				}),
+			),
+			17 => ("syncNative", json!({ "account": account(ix, 0)? })),
+			18 => (
+				"initializeAccount3",
+				json!({
+					"account": account(ix, 0)?,
+					"mint": account(ix, 1)?,
+					"owner": data.pubkey()?,
+				}),
+			),
+			19 => (
+				"initializeMultisig2",
+				json!({
+					"multisig": account(ix, 0)?,
+					"signers": ix.accounts.iter().skip(1).collect::<Vec<_>>(),
+					"m": data.u8()?,
+				}),
+			),
+			20 => (
+				"initializeMint2",
+				json!({
+					"mint": account(ix, 0)?,
+					"decimals": data.u8()?,
+					"mintAuthority": data.pubkey()?,
+					"freezeAuthority": data.option_pubkey()?,
+				}),
+			),
+			21 => ("getAccountDataSize", json!({ "mint": account(ix, 0)? })),
+			22 => (
+				"initializeImmutableOwner",
+				json!({ "account": account(ix, 0)? }),
+			),
+			23 => (
+				"amountToUiAmount",
+				json!({ "mint": account(ix, 0)?, "amount": data.u64()?.to_string() }),
+			),
+			24 => ("uiAmountToAmount", json!({ "mint": account(ix, 0)? })),
+			tag => match EXTENSIONS.get(tag.wrapping_sub(EXTENSION_START) as usize) {
+				Some(extension) => (*extension, json!({ "accounts": ix.accounts })),
+				None => {
+					return Err(AppError::InstructionDecodeError(format!(
+						"unknown token instruction {}",
+						tag
+					)))
+				}
+			},
+		};
+
+		Ok(DecodedInstruction::new(
+			self.program,
+			instruction_type,
+			info,
+		))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::processor::decoders::TOKEN_PROGRAM_ID;
+
+	#[test]
+	fn test_decode_transfer_checked() {
+		let mut data = vec![12];
+		data.extend(2_500u64.to_le_bytes());
+		data.push(6);
+		let instruction = ProcessedInstruction {
+			instruction_index: 0,
+			inner_index: None,
+			stack_height: None,
+			program_id: TOKEN_PROGRAM_ID.to_string(),
+			accounts: ["source", "mint", "destination", "owner"]
+				.into_iter()
+				.map(String::from)
+				.collect(),
+			data,
+			decoded: None,
+		};
+
+		let decoded = TokenDecoder::new("spl-token").decode(&instruction).unwrap();
+
+		assert_eq!(decoded.instruction_type, "transferChecked");
+		assert_eq!(decoded.info["amount"], "2500");
+		assert_eq!(decoded.info["decimals"], 6);
+	}
+}
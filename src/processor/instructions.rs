@@ -0,0 +1,184 @@
+use crate::models::{AccountKey, DecodedInstruction, ProcessedInstruction};
+use serde_json::Value;
+use solana_transaction_status::{
+	option_serializer::OptionSerializer, UiInstruction, UiMessage, UiParsedInstruction,
+	UiTransactionStatusMeta,
+};
+
+/// Top level instructions of a message. Instructions of raw messages are compiled, they are
+/// wrapped in `UiInstruction` so that both message types are walked the same way.
+pub fn get_message_instructions(message: &UiMessage) -> Vec<UiInstruction> {
+	match message {
+		UiMessage::Raw(msg) => msg
+			.instructions
+			.iter()
+			.cloned()
+			.map(UiInstruction::Compiled)
+			.collect(),
+		UiMessage::Parsed(msg) => msg.instructions.clone(),
+	}
+}
+
+/// Walks the top level instructions of a transaction and the inner instructions each of them
+/// invoked, in execution order. `account_keys` must include lookup table accounts as compiled
+/// instructions reference them by index. Instructions referencing an index outside of
+/// `account_keys` are skipped.
+pub fn get_instructions(
+	top_level: Vec<UiInstruction>,
+	meta: Option<&UiTransactionStatusMeta>,
+	account_keys: &[AccountKey],
+) -> Vec<ProcessedInstruction> {
+	let mut inner_instructions = match meta.map(|meta| meta.inner_instructions.as_ref()) {
+		Some(OptionSerializer::Some(inner)) => inner.clone(),
+		_ => Vec::new(),
+	};
+	inner_instructions.sort_by_key(|inner| inner.index);
+
+	let mut instructions = Vec::new();
+	for (instruction_index, instruction) in top_level.into_iter().enumerate() {
+		let instruction_index = instruction_index as i16;
+		instructions.extend(to_processed(
+			instruction,
+			instruction_index,
+			None,
+			account_keys,
+		));
+
+		let inner = inner_instructions
+			.iter()
+			.filter(|inner| inner.index as i16 == instruction_index)
+			.flat_map(|inner| inner.instructions.iter().cloned());
+		for (inner_index, instruction) in inner.enumerate() {
+			instructions.extend(to_processed(
+				instruction,
+				instruction_index,
+				Some(inner_index as i16),
+				account_keys,
+			));
+		}
+	}
+
+	instructions
+}
+
+/// Resolves the program id and accounts of an instruction, parsed instructions do not carry
+/// their accounts or data and keep the program id and the RPC node's parsed form
+fn to_processed(
+	instruction: UiInstruction,
+	instruction_index: i16,
+	inner_index: Option<i16>,
+	account_keys: &[AccountKey],
+) -> Option<ProcessedInstruction> {
+	let key = |index: u8| {
+		account_keys
+			.get(index as usize)
+			.map(|key| key.pubkey.clone())
+	};
+
+	let (program_id, accounts, data, stack_height, decoded) = match instruction {
+		UiInstruction::Compiled(ix) => (
+			key(ix.program_id_index)?,
+			ix.accounts
+				.into_iter()
+				.map(key)
+				.collect::<Option<Vec<_>>>()?,
+			ix.data,
+			ix.stack_height,
+			None,
+		),
+		UiInstruction::Parsed(UiParsedInstruction::PartiallyDecoded(ix)) => {
+			(ix.program_id, ix.accounts, ix.data, ix.stack_height, None)
+		}
+		UiInstruction::Parsed(UiParsedInstruction::Parsed(ix)) => {
+			let decoded = from_parsed(&ix.program, ix.parsed);
+			(
+				ix.program_id,
+				Vec::new(),
+				String::new(),
+				ix.stack_height,
+				Some(decoded),
+			)
+		}
+	};
+
+	Some(ProcessedInstruction {
+		instruction_index,
+		inner_index,
+		stack_height: stack_height.map(|height| height as i32),
+		program_id,
+		accounts,
+		data: bs58::decode(data).into_vec().ok()?,
+		decoded,
+	})
+}
+
+/// Parsed instructions are usually `{ "type", "info" }`, others such as memos are a bare value
+/// that is kept as the `info`
+fn from_parsed(program: &str, parsed: Value) -> DecodedInstruction {
+	match parsed {
+		Value::Object(mut fields) if fields.contains_key("type") => {
+			let instruction_type = match fields.remove("type") {
+				Some(Value::String(instruction_type)) => instruction_type,
+				_ => String::new(),
+			};
+			let info = fields.remove("info").unwrap_or(Value::Null);
+			DecodedInstruction::new(program, &instruction_type, info)
+		}
+		parsed => DecodedInstruction::new(program, "", parsed),
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use serde_json::json;
+
+	#[test]
+	fn test_inner_instructions_follow_their_parent() {
+		let top_level: Vec<UiInstruction> = serde_json::from_value(json!([
+			{ "programIdIndex": 2, "accounts": [0, 1], "data": "3Bxs4h24hBtQy9rw", "stackHeight": null },
+			{ "programIdIndex": 3, "accounts": [], "data": "", "stackHeight": null }
+		]))
+		.unwrap();
+		let meta: UiTransactionStatusMeta = serde_json::from_value(json!({
+			"err": null,
+			"status": { "Ok": null },
+			"fee": 5000,
+			"preBalances": [],
+			"postBalances": [],
+			"innerInstructions": [{
+				"index": 0,
+				"instructions": [
+					{ "programIdIndex": 3, "accounts": [1], "data": "", "stackHeight": 2 }
+				]
+			}]
+		}))
+		.unwrap();
+		let account_keys: Vec<AccountKey> = ["a", "b", "program", "memo"]
+			.into_iter()
+			.map(|pubkey| AccountKey {
+				pubkey: pubkey.to_string(),
+				signer: false,
+				writable: false,
+				from_lookup_table: false,
+			})
+			.collect();
+
+		let instructions = get_instructions(top_level, Some(&meta), &account_keys);
+		let order: Vec<(i16, Option<i16>, &str)> = instructions
+			.iter()
+			.map(|ix| (ix.instruction_index, ix.inner_index, ix.program_id.as_str()))
+			.collect();
+
+		assert_eq!(
+			order,
+			vec![
+				(0, None, "program"),
+				(0, Some(0), "memo"),
+				(1, None, "memo")
+			]
+		);
+		assert_eq!(instructions[0].accounts, vec!["a", "b"]);
+		assert_eq!(instructions[1].stack_height, Some(2));
+	}
+}
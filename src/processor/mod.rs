@@ -1,6 +1,10 @@
+pub mod decoders;
+pub mod instructions;
 pub mod processable;
 pub mod process_block;
 pub mod processing_worker;
+pub mod token_balances;
 
-pub use processable::Processable;
+pub use decoders::{DecoderRegistry, InstructionDecoder};
+pub use processable::{Processable, ProcessingContext};
 pub use processing_worker::{ProcessingWorker, ProcessingWorkerManager};
@@ -1,69 +1,195 @@
-use super::Processable;
+use super::{
+	instructions::{get_instructions, get_message_instructions},
+	token_balances::get_token_balance_changes,
+	Processable, ProcessingContext,
+};
 use crate::{
 	error::{AppError, Result},
-	models::{Aggregate, FromMsg, TryDecode},
+	models::{
+		AccountKey, BlockReward, FromMsg, LamportBalance, ProcessedBlock, ProcessedTransaction,
+		TryDecode,
+	},
 };
 use solana_client::rpc_response::SlotInfo;
 use solana_transaction_status::{
-	EncodedTransactionWithStatusMeta, UiConfirmedBlock, UiTransaction,
+	option_serializer::OptionSerializer, EncodedTransactionWithStatusMeta, Reward,
+	UiConfirmedBlock, UiMessage, UiTransaction, UiTransactionStatusMeta,
 };
 
-use log::{error, info};
+use log::{info, warn};
 
-/// Processes the block into a vector or `Option<Aggregate>` from a UiConfirmedBlock.
-/// The transaction field of a `UiConfirmedBlock` is an `Option<EncodedTransactionWithStatusMeta>`
-/// thus the need for an `Option<Aggregate>` returns an error if the entire Vec is empty
+/// Processes a `UiConfirmedBlock` into a `ProcessedBlock` holding the block header and the
+/// signature, accounts, status, balance changes and instructions of each transaction along with
+/// the block's rewards. The transaction field of a `UiConfirmedBlock` is an
+/// `Option<Vec<EncodedTransactionWithStatusMeta>>`, transactions that cannot be decoded are
+/// skipped. A produced block without transactions is still stored with its header so that the
+/// slot is not reported as missing.
 impl Processable for (SlotInfo, UiConfirmedBlock) {
-	type Output = Vec<Option<Aggregate>>;
-	fn process(&self) -> Result<Self::Output> {
-		let block_time = self.1.block_time.ok_or(AppError::NoData)?;
-		let transaction_data: Vec<Option<Aggregate>> = self
+	type Output = ProcessedBlock;
+	fn process(&self, context: &ProcessingContext) -> Result<Self::Output> {
+		let transactions: Vec<ProcessedTransaction> = self
 			.1
 			.transactions
 			.clone()
-			.ok_or(AppError::NoData)?
+			.unwrap_or_default()
 			.into_iter()
-			.flat_map(|tx: EncodedTransactionWithStatusMeta| {
-				match get_accounts_from_transaction(tx) {
-					Some((signature, account_keys)) => {
-						let aggregates = account_keys
-							.into_iter()
-							.map(|account| {
-								Some(Aggregate {
-									blockhash: self.1.blockhash.clone(),
-									slot: self.0.slot as i64,
-									block_time,
-									signature: signature.clone(),
-									account,
-								})
-							})
-							.collect::<Vec<_>>();
-						Some(aggregates).into_iter().flatten().collect()
-					}
-					None => Vec::new(),
-				}
-			})
+			.filter_map(|tx| get_transaction_data(tx, context))
 			.collect();
 
-        // Error if completely empty
-		if transaction_data.is_empty() {
-			error!("No transaction data");
-			return Err(AppError::NoData);
+		if transactions.is_empty() {
+			info!(
+				"Block {} has no transactions, storing its header",
+				self.0.slot
+			);
 		}
 
-		info!("Block processed: {}", self.1.blockhash);
-		Ok(transaction_data)
+		let rewards = get_block_rewards(self.1.rewards.as_deref().unwrap_or_default());
+
+		// Blocks built from a watched transaction have no header, their blockhash is empty
+		let has_header = !self.1.blockhash.is_empty();
+
+		info!("Block processed: {}", self.0.slot);
+		Ok(ProcessedBlock {
+			slot: self.0.slot as i64,
+			blockhash: has_header.then(|| self.1.blockhash.clone()),
+			block_time: self.1.block_time,
+			parent_slot: has_header.then_some(self.1.parent_slot as i64),
+			block_height: self.1.block_height.map(|height| height as i64),
+			previous_blockhash: has_header.then(|| self.1.previous_blockhash.clone()),
+			rewards,
+			transactions,
+		})
 	}
 }
 
-/// Decodes and retrieves transaction signature and associated accounts from an
-/// `EncodedTransactionWithStatusMeta` returns `Some<(String, Vec<String>)`, if 
-/// `EncodedTransactionWithStatusMeta` is not `None`
-fn get_accounts_from_transaction(
-	transaction: EncodedTransactionWithStatusMeta,
-) -> Option<(String, Vec<String>)> {
+/// Decodes and retrieves transaction signature, associated accounts and status from an
+/// `EncodedTransactionWithStatusMeta` returns `Some<ProcessedTransaction>`, if
+/// `EncodedTransactionWithStatusMeta` is not `None`. The RPC node omits meta when it is
+/// unavailable, transactions without meta are kept with no fee and an unknown status. Instructions are
+/// decoded with the decoders registered in the context.
+fn get_transaction_data(
+	mut transaction: EncodedTransactionWithStatusMeta,
+	context: &ProcessingContext,
+) -> Option<ProcessedTransaction> {
+	let meta = transaction.meta.take();
 	let ui_tx = UiTransaction::try_decode(transaction).ok()?;
 	let signature = &ui_tx.signatures[0];
-	let account_keys = <Vec<String>>::from_msg(ui_tx.message);
-	Some((signature.to_string(), account_keys))
+
+	// Accounts of parsed messages already include lookup table accounts, raw messages only hold
+	// the static keys and the loaded addresses follow them in the order writable then readonly
+	let is_raw = matches!(ui_tx.message, UiMessage::Raw(_));
+	let top_level = get_message_instructions(&ui_tx.message);
+	let mut account_keys = <Vec<AccountKey>>::from_msg(ui_tx.message);
+	if let (true, Some(meta)) = (is_raw, &meta) {
+		account_keys.extend(get_loaded_accounts(meta));
+	}
+
+	let mut processed = ProcessedTransaction {
+		signature: signature.to_string(),
+		accounts: account_keys,
+		err: None,
+		fee: None,
+		compute_units_consumed: None,
+		log_message_count: None,
+		token_balance_changes: Vec::new(),
+		lamport_balances: Vec::new(),
+		instructions: Vec::new(),
+	};
+	processed.instructions = get_instructions(top_level, meta.as_ref(), &processed.accounts);
+	for instruction in processed.instructions.iter_mut() {
+		if instruction.decoded.is_none() {
+			instruction.decoded = context.decoders.decode(instruction);
+		}
+	}
+	if let Some(meta) = meta {
+		processed.token_balance_changes =
+			match get_token_balance_changes(&meta, &processed.accounts) {
+				Ok(changes) => changes,
+				Err(AppError::EmptyTokenBalances) => Vec::new(),
+				Err(e) => {
+					warn!(
+						"Could not get token balances for {}: {}",
+						processed.signature, e
+					);
+					Vec::new()
+				}
+			};
+		processed.lamport_balances = get_lamport_balances(&meta, processed.accounts.len());
+		set_status_from_meta(&mut processed, meta);
+	}
+
+	Some(processed)
+}
+
+/// Copies the rewards credited in a block
+fn get_block_rewards(rewards: &[Reward]) -> Vec<BlockReward> {
+	rewards
+		.iter()
+		.map(|reward| BlockReward {
+			pubkey: reward.pubkey.clone(),
+			lamports: reward.lamports,
+			post_balance: reward.post_balance as i64,
+			reward_type: reward
+				.reward_type
+				.map(|reward_type| reward_type.to_string()),
+			commission: reward.commission.map(|commission| commission as i16),
+		})
+		.collect()
+}
+
+/// Accounts loaded through address lookup tables of a v0 transaction
+fn get_loaded_accounts(meta: &UiTransactionStatusMeta) -> Vec<AccountKey> {
+	let loaded = match meta.loaded_addresses.as_ref() {
+		OptionSerializer::Some(loaded) => loaded,
+		_ => return Vec::new(),
+	};
+
+	let writable = loaded.writable.iter().map(|pubkey| (pubkey, true));
+	let readonly = loaded.readonly.iter().map(|pubkey| (pubkey, false));
+	writable
+		.chain(readonly)
+		.map(|(pubkey, writable)| AccountKey {
+			pubkey: pubkey.clone(),
+			signer: false,
+			writable,
+			from_lookup_table: true,
+		})
+		.collect()
+}
+
+/// Pairs `pre_balances` and `post_balances` by account index, they follow the same order as the
+/// account keys including lookup table accounts. Balances that do not line up with the accounts
+/// are discarded rather than attributed to the wrong account.
+fn get_lamport_balances(
+	meta: &UiTransactionStatusMeta,
+	account_count: usize,
+) -> Vec<LamportBalance> {
+	if meta.pre_balances.len() != account_count || meta.post_balances.len() != account_count {
+		warn!(
+			"Balances do not match account keys: {} accounts, {} pre, {} post",
+			account_count,
+			meta.pre_balances.len(),
+			meta.post_balances.len()
+		);
+		return Vec::new();
+	}
+
+	meta.pre_balances
+		.iter()
+		.zip(&meta.post_balances)
+		.map(|(&pre, &post)| LamportBalance {
+			pre: pre as i64,
+			post: post as i64,
+		})
+		.collect()
+}
+
+/// Copies the error, fee, compute units and log message count from a transaction's meta
+fn set_status_from_meta(processed: &mut ProcessedTransaction, meta: UiTransactionStatusMeta) {
+	processed.err = meta.err.map(|e| e.to_string());
+	processed.fee = Some(meta.fee as i64);
+	processed.compute_units_consumed =
+		Option::<u64>::from(meta.compute_units_consumed).map(|units| units as i64);
+	processed.log_message_count =
+		Option::<Vec<String>>::from(meta.log_messages).map(|logs| logs.len() as i32);
 }
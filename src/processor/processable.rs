@@ -1,7 +1,30 @@
+use super::decoders::DecoderRegistry;
 use crate::error::Result;
+use std::sync::Arc;
 
 /// Trait to ensure that `ProcessingWorker` is reusuable for any type that can be processed
 pub trait Processable: Send + 'static {
 	type Output: Send;
-	fn process(&self) -> Result<Self::Output>;
+	fn process(&self, context: &ProcessingContext) -> Result<Self::Output>;
+}
+
+/// State shared by all processing workers, cloning is cheap
+#[derive(Clone)]
+pub struct ProcessingContext {
+	pub decoders: Arc<DecoderRegistry>,
+}
+
+impl ProcessingContext {
+	pub fn new(decoders: DecoderRegistry) -> Self {
+		Self {
+			decoders: Arc::new(decoders),
+		}
+	}
+}
+
+/// Context with the built in instruction decoders
+impl Default for ProcessingContext {
+	fn default() -> Self {
+		Self::new(DecoderRegistry::with_builtin())
+	}
 }
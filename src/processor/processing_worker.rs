@@ -1,4 +1,5 @@
-use super::Processable;
+use super::{Processable, ProcessingContext};
+use crate::channel::{StageReceiver, StageSender};
 use crate::error::Result;
 use crate::pool::ThreadPool;
 use crate::worker::{Worker, WorkerHandle, WorkerManager};
@@ -6,9 +7,9 @@ use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
 
-/// Generic workers to process a `Processable` type. As crossbeam channels are unbounded,
-/// enough workers should be in the worker pool or to retrieve tasks from the receiving channel.
-/// A memory leak can occur if there are too few workers as the crossbeam channels are unbounded
+/// Generic workers to process a `Processable` type. Enough workers should be in the worker pool
+/// to retrieve tasks from the receiving channel, if there are too few workers the upstream stage
+/// is throttled according to its overflow policy.
 pub struct ProcessingWorkerManager<T>
 where
 	T: Processable,
@@ -16,8 +17,9 @@ where
 {
 	pool: Arc<ThreadPool>,
 	workers: Vec<WorkerHandle>,
-	proc_rx: crossbeam_channel::Receiver<T>,
-	storage_tx: crossbeam_channel::Sender<T::Output>,
+	proc_rx: StageReceiver<T>,
+	storage_tx: StageSender<T::Output>,
+	context: ProcessingContext,
 }
 
 impl<T> ProcessingWorkerManager<T>
@@ -26,9 +28,10 @@ where
 	T::Output: Send + 'static,
 {
 	pub fn new(
-		proc_rx: crossbeam_channel::Receiver<T>,
-		storage_tx: crossbeam_channel::Sender<T::Output>,
+		proc_rx: StageReceiver<T>,
+		storage_tx: StageSender<T::Output>,
 		worker_threads: usize,
+		context: ProcessingContext,
 	) -> Self {
 		let pool = Arc::new(ThreadPool::new(worker_threads));
 
@@ -37,6 +40,7 @@ where
 			workers: Vec::with_capacity(worker_threads),
 			proc_rx,
 			storage_tx,
+			context,
 		}
 	}
 
@@ -76,6 +80,7 @@ where
 			let worker = ProcessingWorker::new(
 				self.proc_rx.clone(),
 				self.storage_tx.clone(),
+				self.context.clone(),
 				Arc::clone(&self.pool),
 			);
 
@@ -114,23 +119,27 @@ pub struct ProcessingWorker<T: Processable>
 where
 	T::Output: Send,
 {
-	proc_rx: crossbeam_channel::Receiver<T>,
-	storage_tx: crossbeam_channel::Sender<T::Output>,
+	proc_rx: StageReceiver<T>,
+	storage_tx: StageSender<T::Output>,
+	context: ProcessingContext,
 }
 
 impl<T: Processable> ProcessingWorker<T>
 where
 	T::Output: Send + 'static,
 {
+	#[allow(clippy::new_ret_no_self)]
 	pub fn new(
-		proc_rx: crossbeam_channel::Receiver<T>,
-		storage_tx: crossbeam_channel::Sender<T::Output>,
+		proc_rx: StageReceiver<T>,
+		storage_tx: StageSender<T::Output>,
+		context: ProcessingContext,
 		thread_pool: Arc<ThreadPool>,
 	) -> WorkerHandle {
 		WorkerHandle::new(
 			Self {
 				proc_rx,
 				storage_tx,
+				context,
 			},
 			thread_pool,
 		)
@@ -148,7 +157,7 @@ where
 				match self.proc_rx.recv() {
 					Ok(data) => {
 						log::debug!("[PROCESSING] Queue length: {}", self.proc_rx.len());
-						let processed = match data.process() {
+						let processed = match data.process(&self.context) {
 							Ok(data) => data,
 							Err(e) => {
 								log::error!("[PROCESSING] Could not process block: {}", e);
@@ -156,7 +165,7 @@ where
 								continue;
 							}
 						};
-						match self.storage_tx.send(processed) {
+						match self.storage_tx.send_async(processed).await {
 							Ok(..) => continue,
 							Err(e) => {
 								log::error!("Error sending to storage worker: {}", e);
@@ -0,0 +1,132 @@
+use crate::{
+	error::{AppError, Result},
+	models::{AccountKey, TokenBalanceChange},
+};
+use solana_transaction_status::{
+	option_serializer::OptionSerializer, UiTransactionStatusMeta, UiTransactionTokenBalance,
+};
+use std::collections::BTreeMap;
+
+/// Pre and post balance of a token account
+type BalancePair<'a> = (
+	Option<&'a UiTransactionTokenBalance>,
+	Option<&'a UiTransactionTokenBalance>,
+);
+
+/// Derives the change in balance of every token account touched by a transaction from the
+/// `pre_token_balances` and `post_token_balances` of its meta. Balances are matched by account
+/// index, an account missing from either side was created or closed in the transaction and its
+/// balance on that side is zero. Accounts whose balance did not change are left out. Returns
+/// `AppError::EmptyTokenBalances` if the meta has no token balances.
+pub fn get_token_balance_changes(
+	meta: &UiTransactionStatusMeta,
+	account_keys: &[AccountKey],
+) -> Result<Vec<TokenBalanceChange>> {
+	let (pre, post) = match (
+		meta.pre_token_balances.as_ref(),
+		meta.post_token_balances.as_ref(),
+	) {
+		(OptionSerializer::Some(pre), OptionSerializer::Some(post)) => (pre, post),
+		_ => return Err(AppError::EmptyTokenBalances),
+	};
+	if pre.is_empty() && post.is_empty() {
+		return Err(AppError::EmptyTokenBalances);
+	}
+
+	let mut balances: BTreeMap<u8, BalancePair> = BTreeMap::new();
+	for balance in pre {
+		balances.entry(balance.account_index).or_default().0 = Some(balance);
+	}
+	for balance in post {
+		balances.entry(balance.account_index).or_default().1 = Some(balance);
+	}
+
+	let mut changes = Vec::new();
+	for (account_index, (pre, post)) in balances {
+		let pre_amount = pre.map(parse_amount).transpose()?.unwrap_or(0);
+		let post_amount = post.map(parse_amount).transpose()?.unwrap_or(0);
+		if pre_amount == post_amount {
+			continue;
+		}
+
+		// Either side holds the mint, owner and decimals, post is preferred as the most recent
+		let balance = post.or(pre).ok_or(AppError::EmptyTokenBalances)?;
+		let account = account_keys
+			.get(account_index as usize)
+			.ok_or(AppError::ParseTokenType)?;
+
+		changes.push(TokenBalanceChange {
+			account_index: account_index as i16,
+			account: account.pubkey.clone(),
+			mint: balance.mint.clone(),
+			owner: Option::<&String>::from(balance.owner.as_ref()).cloned(),
+			decimals: balance.ui_token_amount.decimals as i16,
+			pre_amount,
+			post_amount,
+			delta: post_amount as i128 - pre_amount as i128,
+		});
+	}
+
+	Ok(changes)
+}
+
+/// Token amounts are returned as strings of the raw amount
+fn parse_amount(balance: &UiTransactionTokenBalance) -> Result<u64> {
+	balance
+		.ui_token_amount
+		.amount
+		.parse()
+		.map_err(|_| AppError::ParseTokenType)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use serde_json::json;
+
+	fn token_balance(account_index: u8, amount: &str) -> serde_json::Value {
+		json!({
+			"accountIndex": account_index,
+			"mint": "So11111111111111111111111111111111111111112",
+			"owner": "11111111111111111111111111111111",
+			"uiTokenAmount": {
+				"uiAmount": null,
+				"decimals": 9,
+				"amount": amount,
+				"uiAmountString": "0"
+			}
+		})
+	}
+
+	#[test]
+	fn test_token_balance_changes() {
+		let meta: UiTransactionStatusMeta = serde_json::from_value(json!({
+			"err": null,
+			"status": { "Ok": null },
+			"fee": 5000,
+			"preBalances": [],
+			"postBalances": [],
+			"preTokenBalances": [token_balance(1, "100"), token_balance(2, "50")],
+			"postTokenBalances": [token_balance(1, "40"), token_balance(2, "50"), token_balance(3, "60")]
+		}))
+		.unwrap();
+		let account_keys: Vec<AccountKey> = ["a", "b", "c", "d"]
+			.into_iter()
+			.map(|pubkey| AccountKey {
+				pubkey: pubkey.to_string(),
+				signer: false,
+				writable: true,
+				from_lookup_table: false,
+			})
+			.collect();
+
+		let changes = get_token_balance_changes(&meta, &account_keys).unwrap();
+		let deltas: Vec<(&str, i128)> = changes
+			.iter()
+			.map(|change| (change.account.as_str(), change.delta))
+			.collect();
+
+		assert_eq!(deltas, vec![("b", -60), ("d", 60)]);
+		assert_eq!(changes[1].pre_amount, 0);
+	}
+}
@@ -2,5 +2,5 @@ pub mod storable;
 pub mod storage_worker;
 pub mod store_block;
 
-pub use storable::Storable;
+pub use storable::{Storable, StoreReport};
 pub use storage_worker::{StorageWorker, StorageWorkerManager};
@@ -1,12 +1,21 @@
+use crate::config::StorageConfig;
 use crate::database::DatabasePool;
 use crate::error::Result;
 use std::future::Future;
 use std::pin::Pin;
 
+/// Number of rows written by a `Storable` and the number skipped as already stored
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StoreReport {
+	pub inserted: u64,
+	pub duplicates: u64,
+}
+
 /// Trait to ensure that StorageWorker is reusable for any type that can be stored
 pub trait Storable: Sized + Send {
 	fn store(
 		self,
 		db_pool: DatabasePool,
-	) -> Result<Pin<Box<dyn Future<Output = Result<()>> + Send>>>;
+		config: &StorageConfig,
+	) -> Result<Pin<Box<dyn Future<Output = Result<StoreReport>> + Send>>>;
 }
@@ -1,4 +1,6 @@
 use super::Storable;
+use crate::channel::StageReceiver;
+use crate::config::StorageConfig;
 use crate::database::DatabasePool;
 use crate::error::Result;
 use crate::pool::ThreadPool;
@@ -16,10 +18,11 @@ pub struct StorageWorkerManager<T>
 where
 	T: Storable + 'static,
 {
+	config: StorageConfig,
 	pool: Arc<ThreadPool>,
 	db_pool: DatabasePool,
 	workers: Vec<WorkerHandle>,
-	storage_rx: crossbeam_channel::Receiver<T>,
+	storage_rx: StageReceiver<T>,
 }
 
 impl<T> StorageWorkerManager<T>
@@ -27,13 +30,15 @@ where
 	T: Storable + 'static,
 {
 	pub async fn new(
+		config: StorageConfig,
 		db_pool: Arc<Pool<PostgresConnectionManager<NoTls>>>,
-		storage_rx: crossbeam_channel::Receiver<T>,
-		worker_threads: usize,
+		storage_rx: StageReceiver<T>,
 	) -> Self {
+		let worker_threads = config.worker_threads as usize;
 		let pool = Arc::new(ThreadPool::new(worker_threads));
 
 		Self {
+			config,
 			pool,
 			db_pool,
 			workers: Vec::with_capacity(worker_threads),
@@ -76,6 +81,7 @@ where
 	fn spawn_worker(&mut self) -> Pin<Box<dyn Future<Output = ()> + Send + '_>> {
 		Box::pin(async move {
 			let worker = StorageWorker::new(
+				self.config.clone(),
 				self.storage_rx.clone(),
 				Arc::clone(&self.pool),
 				Arc::clone(&self.db_pool),
@@ -117,13 +123,14 @@ where
 /// Receives messages on a crossbeam channel, crossbeam channels are meant to be thread safe
 /// and should not require locking. Each worker will pull a task off the channel as soon as it
 /// arrives provided there are idle workers/enough workers in the pool. If enough workers aren't
-/// defined or database writes are slow, this can cause a memory leak as the crossbeam channel
-/// is unbounded.
+/// defined or database writes are slow the storage channel fills up and the processing workers
+/// block until there is room, which in turn throttles the rest of the pipeline.
 pub struct StorageWorker<T>
 where
 	T: Storable,
 {
-	storage_rx: crossbeam_channel::Receiver<T>,
+	config: StorageConfig,
+	storage_rx: StageReceiver<T>,
 	db_pool: DatabasePool,
 }
 
@@ -131,13 +138,16 @@ impl<T> StorageWorker<T>
 where
 	T: Storable + Send + 'static,
 {
+	#[allow(clippy::new_ret_no_self)]
 	pub fn new(
-		storage_rx: crossbeam_channel::Receiver<T>,
+		config: StorageConfig,
+		storage_rx: StageReceiver<T>,
 		thread_pool: Arc<ThreadPool>,
 		db_pool: DatabasePool,
 	) -> WorkerHandle {
 		WorkerHandle::new(
 			Self {
+				config,
 				storage_rx,
 				db_pool,
 			},
@@ -157,8 +167,15 @@ where
 				match self.storage_rx.recv() {
 					Ok(data) => {
 						log::debug!("[STORAGE] Queue length: {}", self.storage_rx.len());
-						match data.store(self.db_pool.clone())?.await {
-							Ok(..) => continue,
+						match data.store(self.db_pool.clone(), &self.config)?.await {
+							Ok(report) => {
+								log::info!(
+									"[STORAGE] Inserted {} rows, skipped {} duplicates",
+									report.inserted,
+									report.duplicates
+								);
+								continue;
+							}
 							Err(e) => {
 								log::error!("Database error: {}", e);
 								continue;
@@ -1,19 +1,40 @@
-use super::Storable;
+use super::{Storable, StoreReport};
 use crate::{
+	config::{StorageConfig, WriteMode},
 	database::DatabasePool,
 	error::{AppError, Result},
-	models::Aggregate,
+	models::{hash_bytes, pubkey_bytes, signature_bytes, ProcessedBlock},
 };
+use std::collections::HashMap;
 use std::{future::Future, pin::Pin};
+use tokio_postgres::{binary_copy::BinaryCopyInWriter, types::Type, Transaction};
 
-/// Takes a Vec<Option<Aggregate>> and structures it as a database transaction to store in the database. 
-/// Uses a transaction so that database is not written to in a loop which is slow.
-impl Storable for Vec<Option<Aggregate>> {
+/// A `transaction_accounts` row ready to be written
+struct AccountRow {
+	transaction_id: i64,
+	account_index: i16,
+	account: Vec<u8>,
+	is_signer: bool,
+	is_writable: bool,
+	from_lookup_table: bool,
+	pre_balance: Option<i64>,
+	post_balance: Option<i64>,
+}
+
+/// Takes a `ProcessedBlock` and structures it as a database transaction to store in the database.
+/// Uses a transaction so that database is not written to in a loop which is slow. The block and
+/// its transactions are written first, account rows are either inserted one statement at a time
+/// or streamed with a binary `COPY` depending on `write_mode`. Rows that are already stored are
+/// skipped so that retries and backfills of a block succeed, the report counts account rows.
+/// Token balance changes and instructions are written alongside in the same transaction.
+impl Storable for ProcessedBlock {
 	fn store(
 		self,
 		db_pool: DatabasePool,
-	) -> Result<Pin<Box<dyn Future<Output = Result<()>> + Send>>> {
+		config: &StorageConfig,
+	) -> Result<Pin<Box<dyn Future<Output = Result<StoreReport>> + Send>>> {
 		let db_pool_clone = db_pool.clone();
+		let write_mode = config.write_mode;
 
 		Ok(Box::pin(async move {
 			let mut conn = db_pool_clone.get().await.map_err(|e| {
@@ -26,39 +47,629 @@ impl Storable for Vec<Option<Aggregate>> {
 				AppError::DatabaseError(e)
 			})?;
 
-			for tx in self {
-				if let Some(tx) = tx {
-					transaction
-						.execute(
-							"INSERT INTO transaction_accounts (
-                            blockhash, 
-                            slot, 
-                            block_time, 
-                            signature,
-                            account
-                        ) VALUES ($1, $2, $3, $4, $5)",
-							&[
-								&tx.blockhash,
-								&tx.slot,
-								&tx.block_time,
-								&tx.signature,
-								&tx.account,
-							],
-						)
-						.await
-						.map_err(|e| {
-							log::error!("Error inserting data: {}", e);
-							AppError::DatabaseError(e)
-						})?;
+			insert_block(&transaction, &self).await?;
+			let transaction_ids = insert_transactions(&transaction, &self).await?;
+
+			let mut rows = Vec::new();
+			for tx in &self.transactions {
+				let transaction_id = get_transaction_id(&transaction_ids, &tx.signature)?;
+				for (account_index, account) in tx.accounts.iter().enumerate() {
+					let balance = tx.lamport_balances.get(account_index);
+					rows.push(AccountRow {
+						transaction_id,
+						account_index: account_index as i16,
+						account: pubkey_bytes(&account.pubkey)?,
+						is_signer: account.signer,
+						is_writable: account.writable,
+						from_lookup_table: account.from_lookup_table,
+						pre_balance: balance.map(|balance| balance.pre),
+						post_balance: balance.map(|balance| balance.post),
+					});
 				}
 			}
 
+			let inserted = match write_mode {
+				WriteMode::Insert => insert_rows(&transaction, &rows).await?,
+				WriteMode::Copy => copy_rows(&transaction, &rows).await?,
+			};
+
+			insert_token_balance_changes(&transaction, &self, &transaction_ids).await?;
+			insert_instructions(&transaction, &self, &transaction_ids).await?;
+
 			transaction.commit().await.map_err(|e| {
 				log::error!("Error committing transaction: {}", e);
 				AppError::DatabaseError(e)
 			})?;
 
-			Ok(())
+			Ok(StoreReport {
+				inserted,
+				duplicates: rows.len() as u64 - inserted,
+			})
 		}))
 	}
 }
+
+/// A block stored from a watched transaction has no header, it is filled in if the full block is
+/// stored later. `leader` is set by the finalizer
+async fn insert_block(transaction: &Transaction<'_>, block: &ProcessedBlock) -> Result<()> {
+	transaction
+		.execute(
+			"INSERT INTO blocks (
+				slot, blockhash, block_time, parent_slot, block_height, previous_blockhash
+			) VALUES ($1, $2, $3, $4, $5, $6)
+			ON CONFLICT (slot) DO UPDATE SET
+				blockhash = EXCLUDED.blockhash,
+				block_time = COALESCE(EXCLUDED.block_time, blocks.block_time),
+				parent_slot = EXCLUDED.parent_slot,
+				block_height = EXCLUDED.block_height,
+				previous_blockhash = EXCLUDED.previous_blockhash
+			WHERE blocks.blockhash IS NULL AND EXCLUDED.blockhash IS NOT NULL",
+			&[
+				&block.slot,
+				&block.blockhash.as_deref().map(hash_bytes).transpose()?,
+				&block.block_time,
+				&block.parent_slot,
+				&block.block_height,
+				&block
+					.previous_blockhash
+					.as_deref()
+					.map(hash_bytes)
+					.transpose()?,
+			],
+		)
+		.await
+		.map_err(|e| {
+			log::error!("Error inserting block: {}", e);
+			AppError::DatabaseError(e)
+		})?;
+
+	insert_block_rewards(transaction, block).await
+}
+
+/// Inserts the rewards credited in the block in one statement
+async fn insert_block_rewards(transaction: &Transaction<'_>, block: &ProcessedBlock) -> Result<()> {
+	if block.rewards.is_empty() {
+		return Ok(());
+	}
+
+	let reward_indexes: Vec<i16> = (0..block.rewards.len() as i16).collect();
+	let pubkeys = block
+		.rewards
+		.iter()
+		.map(|reward| pubkey_bytes(&reward.pubkey))
+		.collect::<Result<Vec<_>>>()?;
+	let lamports: Vec<i64> = block.rewards.iter().map(|reward| reward.lamports).collect();
+	let post_balances: Vec<i64> = block
+		.rewards
+		.iter()
+		.map(|reward| reward.post_balance)
+		.collect();
+	let reward_types: Vec<Option<String>> = block
+		.rewards
+		.iter()
+		.map(|reward| reward.reward_type.clone())
+		.collect();
+	let commissions: Vec<Option<i16>> = block
+		.rewards
+		.iter()
+		.map(|reward| reward.commission)
+		.collect();
+
+	transaction
+		.execute(
+			"INSERT INTO block_rewards (
+				slot, reward_index, pubkey, lamports, post_balance, reward_type, commission
+			)
+			SELECT $1, * FROM UNNEST(
+				$2::SMALLINT[], $3::BYTEA[], $4::BIGINT[], $5::BIGINT[], $6::TEXT[], $7::SMALLINT[]
+			)
+			ON CONFLICT DO NOTHING",
+			&[
+				&block.slot,
+				&reward_indexes,
+				&pubkeys,
+				&lamports,
+				&post_balances,
+				&reward_types,
+				&commissions,
+			],
+		)
+		.await
+		.map_err(|e| {
+			log::error!("Error inserting block rewards: {}", e);
+			AppError::DatabaseError(e)
+		})?;
+
+	Ok(())
+}
+
+/// Inserts every transaction of the block in one statement and returns the id of each
+/// transaction keyed by signature, including transactions that were already stored. Transactions
+/// are keyed by slot and signature, a transaction included again in a block of another fork is
+/// stored with each block so that it survives the abandoned block being deleted.
+async fn insert_transactions(
+	transaction: &Transaction<'_>,
+	block: &ProcessedBlock,
+) -> Result<HashMap<Vec<u8>, i64>> {
+	let signatures = block
+		.transactions
+		.iter()
+		.map(|tx| signature_bytes(&tx.signature))
+		.collect::<Result<Vec<_>>>()?;
+	let errs: Vec<Option<String>> = block.transactions.iter().map(|tx| tx.err.clone()).collect();
+	let fees: Vec<Option<i64>> = block.transactions.iter().map(|tx| tx.fee).collect();
+	let compute_units: Vec<Option<i64>> = block
+		.transactions
+		.iter()
+		.map(|tx| tx.compute_units_consumed)
+		.collect();
+	let log_message_counts: Vec<Option<i32>> = block
+		.transactions
+		.iter()
+		.map(|tx| tx.log_message_count)
+		.collect();
+
+	transaction
+		.execute(
+			"INSERT INTO transactions (
+				slot, signature, err, fee, compute_units_consumed, log_message_count
+			)
+			SELECT $1, * FROM UNNEST($2::BYTEA[], $3::TEXT[], $4::BIGINT[], $5::BIGINT[], $6::INT[])
+			ON CONFLICT (slot, signature) DO NOTHING",
+			&[
+				&block.slot,
+				&signatures,
+				&errs,
+				&fees,
+				&compute_units,
+				&log_message_counts,
+			],
+		)
+		.await
+		.map_err(|e| {
+			log::error!("Error inserting transactions: {}", e);
+			AppError::DatabaseError(e)
+		})?;
+
+	let rows = transaction
+		.query(
+			"SELECT id, signature FROM transactions WHERE slot = $1 AND signature = ANY($2)",
+			&[&block.slot, &signatures],
+		)
+		.await?;
+
+	Ok(rows
+		.into_iter()
+		.map(|row| (row.get("signature"), row.get("id")))
+		.collect())
+}
+
+fn get_transaction_id(transaction_ids: &HashMap<Vec<u8>, i64>, signature: &str) -> Result<i64> {
+	transaction_ids
+		.get(&signature_bytes(signature)?)
+		.copied()
+		.ok_or(AppError::NoTxid)
+}
+
+/// Inserts the token balance changes of every transaction of the block in one statement, amounts
+/// are sent as text as they do not fit in a `BIGINT`
+async fn insert_token_balance_changes(
+	transaction: &Transaction<'_>,
+	block: &ProcessedBlock,
+	transaction_ids: &HashMap<Vec<u8>, i64>,
+) -> Result<()> {
+	let mut ids: Vec<i64> = Vec::new();
+	let mut account_indexes: Vec<i16> = Vec::new();
+	let mut accounts: Vec<Vec<u8>> = Vec::new();
+	let mut mints: Vec<Vec<u8>> = Vec::new();
+	let mut owners: Vec<Option<Vec<u8>>> = Vec::new();
+	let mut decimals: Vec<i16> = Vec::new();
+	let mut pre_amounts: Vec<String> = Vec::new();
+	let mut post_amounts: Vec<String> = Vec::new();
+	let mut deltas: Vec<String> = Vec::new();
+
+	for tx in &block.transactions {
+		if tx.token_balance_changes.is_empty() {
+			continue;
+		}
+		let transaction_id = get_transaction_id(transaction_ids, &tx.signature)?;
+		for change in &tx.token_balance_changes {
+			ids.push(transaction_id);
+			account_indexes.push(change.account_index);
+			accounts.push(pubkey_bytes(&change.account)?);
+			mints.push(pubkey_bytes(&change.mint)?);
+			owners.push(change.owner.as_deref().map(pubkey_bytes).transpose()?);
+			decimals.push(change.decimals);
+			pre_amounts.push(change.pre_amount.to_string());
+			post_amounts.push(change.post_amount.to_string());
+			deltas.push(change.delta.to_string());
+		}
+	}
+
+	if ids.is_empty() {
+		return Ok(());
+	}
+
+	transaction
+		.execute(
+			"INSERT INTO token_balance_changes (
+				transaction_id, account_index, account, mint, owner, decimals,
+				pre_amount, post_amount, delta
+			)
+			SELECT * FROM UNNEST(
+				$1::BIGINT[], $2::SMALLINT[], $3::BYTEA[], $4::BYTEA[], $5::BYTEA[], $6::SMALLINT[],
+				$7::TEXT[]::NUMERIC[], $8::TEXT[]::NUMERIC[], $9::TEXT[]::NUMERIC[]
+			)
+			ON CONFLICT DO NOTHING",
+			&[
+				&ids,
+				&account_indexes,
+				&accounts,
+				&mints,
+				&owners,
+				&decimals,
+				&pre_amounts,
+				&post_amounts,
+				&deltas,
+			],
+		)
+		.await
+		.map_err(|e| {
+			log::error!("Error inserting token balance changes: {}", e);
+			AppError::DatabaseError(e)
+		})?;
+
+	Ok(())
+}
+
+/// Inserts the program id, position and decoded form of every instruction of the block in one
+/// statement, decoded instructions are sent as text and stored as `JSONB`
+async fn insert_instructions(
+	transaction: &Transaction<'_>,
+	block: &ProcessedBlock,
+	transaction_ids: &HashMap<Vec<u8>, i64>,
+) -> Result<()> {
+	let mut ids: Vec<i64> = Vec::new();
+	let mut positions: Vec<i16> = Vec::new();
+	let mut instruction_indexes: Vec<i16> = Vec::new();
+	let mut inner_indexes: Vec<Option<i16>> = Vec::new();
+	let mut stack_heights: Vec<Option<i32>> = Vec::new();
+	let mut program_ids: Vec<Vec<u8>> = Vec::new();
+	let mut decoded: Vec<Option<String>> = Vec::new();
+
+	for tx in &block.transactions {
+		if tx.instructions.is_empty() {
+			continue;
+		}
+		let transaction_id = get_transaction_id(transaction_ids, &tx.signature)?;
+		for (position, instruction) in tx.instructions.iter().enumerate() {
+			ids.push(transaction_id);
+			positions.push(position as i16);
+			instruction_indexes.push(instruction.instruction_index);
+			inner_indexes.push(instruction.inner_index);
+			stack_heights.push(instruction.stack_height);
+			program_ids.push(pubkey_bytes(&instruction.program_id)?);
+			decoded.push(
+				instruction
+					.decoded
+					.as_ref()
+					.map(serde_json::to_string)
+					.transpose()?,
+			);
+		}
+	}
+
+	if ids.is_empty() {
+		return Ok(());
+	}
+
+	transaction
+		.execute(
+			"INSERT INTO instructions (
+				transaction_id, position, instruction_index, inner_index, stack_height, program_id,
+				decoded
+			)
+			SELECT * FROM UNNEST(
+				$1::BIGINT[], $2::SMALLINT[], $3::SMALLINT[], $4::SMALLINT[], $5::INT[], $6::BYTEA[],
+				$7::TEXT[]::JSONB[]
+			)
+			ON CONFLICT DO NOTHING",
+			&[
+				&ids,
+				&positions,
+				&instruction_indexes,
+				&inner_indexes,
+				&stack_heights,
+				&program_ids,
+				&decoded,
+			],
+		)
+		.await
+		.map_err(|e| {
+			log::error!("Error inserting instructions: {}", e);
+			AppError::DatabaseError(e)
+		})?;
+
+	Ok(())
+}
+
+/// Inserts rows one statement at a time, one round trip per account per transaction. Returns the
+/// number of rows inserted.
+async fn insert_rows(transaction: &Transaction<'_>, rows: &[AccountRow]) -> Result<u64> {
+	let statement = transaction
+		.prepare(
+			"INSERT INTO transaction_accounts (
+            transaction_id,
+            account_index,
+            account,
+            is_signer,
+            is_writable,
+            from_lookup_table,
+            pre_balance,
+            post_balance
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        ON CONFLICT DO NOTHING",
+		)
+		.await?;
+
+	let mut inserted = 0;
+	for row in rows {
+		inserted += transaction
+			.execute(
+				&statement,
+				&[
+					&row.transaction_id,
+					&row.account_index,
+					&row.account,
+					&row.is_signer,
+					&row.is_writable,
+					&row.from_lookup_table,
+					&row.pre_balance,
+					&row.post_balance,
+				],
+			)
+			.await
+			.map_err(|e| {
+				log::error!("Error inserting data: {}", e);
+				AppError::DatabaseError(e)
+			})?;
+	}
+
+	Ok(inserted)
+}
+
+/// Streams all rows in a single binary `COPY`. `COPY` cannot skip conflicting rows so they are
+/// copied into a temporary staging table first and moved over with `ON CONFLICT DO NOTHING`.
+/// Returns the number of rows inserted.
+async fn copy_rows(transaction: &Transaction<'_>, rows: &[AccountRow]) -> Result<u64> {
+	transaction
+		.batch_execute(
+			"CREATE TEMP TABLE staging_transaction_accounts
+			(LIKE transaction_accounts INCLUDING DEFAULTS) ON COMMIT DROP",
+		)
+		.await?;
+
+	let sink = transaction
+		.copy_in(
+			"COPY staging_transaction_accounts (
+				transaction_id, account_index, account, is_signer, is_writable, from_lookup_table,
+				pre_balance, post_balance
+			) FROM STDIN BINARY",
+		)
+		.await?;
+	let writer = BinaryCopyInWriter::new(
+		sink,
+		&[
+			Type::INT8,
+			Type::INT2,
+			Type::BYTEA,
+			Type::BOOL,
+			Type::BOOL,
+			Type::BOOL,
+			Type::INT8,
+			Type::INT8,
+		],
+	);
+	futures::pin_mut!(writer);
+
+	for row in rows {
+		writer
+			.as_mut()
+			.write(&[
+				&row.transaction_id,
+				&row.account_index,
+				&row.account,
+				&row.is_signer,
+				&row.is_writable,
+				&row.from_lookup_table,
+				&row.pre_balance,
+				&row.post_balance,
+			])
+			.await
+			.map_err(|e| {
+				log::error!("Error copying data: {}", e);
+				AppError::DatabaseError(e)
+			})?;
+	}
+
+	writer.finish().await.map_err(|e| {
+		log::error!("Error finishing copy: {}", e);
+		AppError::DatabaseError(e)
+	})?;
+
+	let inserted = transaction
+		.execute(
+			"INSERT INTO transaction_accounts (
+				transaction_id, account_index, account, is_signer, is_writable, from_lookup_table,
+				pre_balance, post_balance
+			)
+			SELECT transaction_id, account_index, account, is_signer, is_writable, from_lookup_table,
+				pre_balance, post_balance
+			FROM staging_transaction_accounts
+			ON CONFLICT DO NOTHING",
+			&[],
+		)
+		.await
+		.map_err(|e| {
+			log::error!("Error inserting staged data: {}", e);
+			AppError::DatabaseError(e)
+		})?;
+
+	Ok(inserted)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::{
+		config::load_config,
+		database::create_database_pool,
+		models::{AccountKey, ProcessedTransaction},
+	};
+	use solana_sdk::{hash::Hash, pubkey::Pubkey, signature::Signature};
+
+	fn block(slot: i64, signature: &str) -> ProcessedBlock {
+		ProcessedBlock {
+			slot,
+			blockhash: Some(Hash::new_unique().to_string()),
+			block_time: Some(0),
+			parent_slot: Some(slot - 1),
+			block_height: None,
+			previous_blockhash: Some(Hash::new_unique().to_string()),
+			rewards: Vec::new(),
+			transactions: vec![ProcessedTransaction {
+				signature: signature.to_string(),
+				accounts: vec![AccountKey {
+					pubkey: Pubkey::new_unique().to_string(),
+					signer: true,
+					writable: true,
+					from_lookup_table: false,
+				}],
+				err: None,
+				fee: Some(5000),
+				compute_units_consumed: None,
+				log_message_count: None,
+				token_balance_changes: Vec::new(),
+				lamport_balances: Vec::new(),
+				instructions: Vec::new(),
+			}],
+		}
+	}
+
+	/// Requires a database configured in `Config.toml`, slots below zero are used and removed
+	#[tokio::test]
+	async fn test_reincluded_transaction_survives_orphaned_block() {
+		let config = load_config("Config.toml").unwrap();
+		let db_pool = create_database_pool(&config.database).await.unwrap();
+		let signature = Signature::new_unique().to_string();
+		let (orphaned_slot, canonical_slot) = (-2_000_010, -2_000_000);
+
+		for slot in [orphaned_slot, canonical_slot] {
+			block(slot, &signature)
+				.store(db_pool.clone(), &config.storage)
+				.unwrap()
+				.await
+				.unwrap();
+		}
+
+		// The finalizer deletes the block of the abandoned fork
+		let conn = db_pool.get().await.unwrap();
+		conn.execute("DELETE FROM blocks WHERE slot = $1", &[&orphaned_slot])
+			.await
+			.unwrap();
+		let rows = conn
+			.query(
+				"SELECT t.slot FROM transactions t
+				JOIN transaction_accounts a ON a.transaction_id = t.id
+				WHERE t.signature = $1",
+				&[&signature_bytes(&signature).unwrap()],
+			)
+			.await
+			.unwrap();
+
+		conn.execute("DELETE FROM blocks WHERE slot = $1", &[&canonical_slot])
+			.await
+			.unwrap();
+
+		assert_eq!(rows.len(), 1);
+		assert_eq!(rows[0].get::<_, i64>("slot"), canonical_slot);
+	}
+
+	/// Requires a database configured in `Config.toml`, slots below zero are used and removed
+	#[tokio::test]
+	async fn test_headerless_block_is_filled_in_by_full_block() {
+		let config = load_config("Config.toml").unwrap();
+		let db_pool = create_database_pool(&config.database).await.unwrap();
+		let slot = -2_000_020;
+
+		// A watched transaction is stored before the full block of its slot
+		let full = block(slot, &Signature::new_unique().to_string());
+		let headerless = ProcessedBlock {
+			blockhash: None,
+			parent_slot: None,
+			previous_blockhash: None,
+			..block(slot, &Signature::new_unique().to_string())
+		};
+		for block in [headerless, full.clone()] {
+			block
+				.store(db_pool.clone(), &config.storage)
+				.unwrap()
+				.await
+				.unwrap();
+		}
+
+		let conn = db_pool.get().await.unwrap();
+		let row = conn
+			.query_one(
+				"SELECT blockhash, parent_slot, (SELECT COUNT(*) FROM transactions WHERE slot = $1)
+				AS transaction_count
+				FROM blocks WHERE slot = $1",
+				&[&slot],
+			)
+			.await
+			.unwrap();
+
+		conn.execute("DELETE FROM blocks WHERE slot = $1", &[&slot])
+			.await
+			.unwrap();
+
+		let blockhash = hash_bytes(full.blockhash.as_deref().unwrap()).unwrap();
+		assert_eq!(row.get::<_, Vec<u8>>("blockhash"), blockhash);
+		assert_eq!(row.get::<_, Option<i64>>("parent_slot"), Some(slot - 1));
+		assert_eq!(row.get::<_, i64>("transaction_count"), 2);
+	}
+
+	/// Requires a database configured in `Config.toml`, slots below zero are used and removed
+	#[tokio::test]
+	async fn test_block_without_transactions_is_stored() {
+		let config = load_config("Config.toml").unwrap();
+		let db_pool = create_database_pool(&config.database).await.unwrap();
+		let slot = -2_000_030;
+
+		ProcessedBlock {
+			block_time: None,
+			transactions: Vec::new(),
+			..block(slot, &Signature::new_unique().to_string())
+		}
+		.store(db_pool.clone(), &config.storage)
+		.unwrap()
+		.await
+		.unwrap();
+
+		let conn = db_pool.get().await.unwrap();
+		let row = conn
+			.query_one(
+				"SELECT blockhash, block_time FROM blocks WHERE slot = $1",
+				&[&slot],
+			)
+			.await
+			.unwrap();
+
+		conn.execute("DELETE FROM blocks WHERE slot = $1", &[&slot])
+			.await
+			.unwrap();
+
+		assert!(row.get::<_, Option<Vec<u8>>>("blockhash").is_some());
+		assert_eq!(row.get::<_, Option<i64>>("block_time"), None);
+	}
+}